@@ -30,6 +30,7 @@ pub fn error_string(err: ffi::cl_int) -> &'static str {
         ffi::CL_IMAGE_FORMAT_NOT_SUPPORTED => "image format not supported",
         ffi::CL_BUILD_PROGRAM_FAILURE => "build program failure",
         ffi::CL_MAP_FAILURE => "map failure",
+        ffi::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST => "exec status error for events in wait list",
         ffi::CL_INVALID_VALUE => "invalid value",
         ffi::CL_INVALID_DEVICE_TYPE => "invalid device type",
         ffi::CL_INVALID_PLATFORM => "invalid platform",
@@ -18,3 +18,6 @@ pub use wrapper::types::command_queue::{self, CommandQueue};
 pub use wrapper::types::program::{self, Program};
 pub use wrapper::types::mem::{self, Buffer};
 pub use wrapper::types::kernel::{self, Kernel};
+pub use wrapper::types::event::{self, Event};
+pub use wrapper::types::command_list::{self, CommandList};
+pub use wrapper::types::stream::{self, Stream};
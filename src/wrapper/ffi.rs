@@ -216,8 +216,8 @@ pub const CL_MEM_HOST_WRITE_ONLY: cl_mem_flags = 128;
 pub const CL_MEM_HOST_READ_ONLY: cl_mem_flags = 256;
 pub const CL_MEM_HOST_NO_ACCESS: cl_mem_flags = 512;
 
-pub const CL_MIGRATE_MEM_OBJECT_HOST: cl_int = 1;
-pub const CL_MIGRATE_MEM_OBJECT_CONTENT_UNDEFINED: cl_int = 2;
+pub const CL_MIGRATE_MEM_OBJECT_HOST: cl_mem_migration_flags = 1;
+pub const CL_MIGRATE_MEM_OBJECT_CONTENT_UNDEFINED: cl_mem_migration_flags = 2;
 pub const CL_R: cl_int = 4272;
 pub const CL_A: cl_int = 4273;
 pub const CL_RG: cl_int = 4274;
@@ -291,9 +291,9 @@ pub const CL_SAMPLER_CONTEXT: cl_int = 4433;
 pub const CL_SAMPLER_NORMALIZED_COORDS: cl_int = 4434;
 pub const CL_SAMPLER_ADDRESSING_MODE: cl_int = 4435;
 pub const CL_SAMPLER_FILTER_MODE: cl_int = 4436;
-pub const CL_MAP_READ: cl_int = 1;
-pub const CL_MAP_WRITE: cl_int = 2;
-pub const CL_MAP_WRITE_INVALIDATE_REGION: cl_int = 4;
+pub const CL_MAP_READ: cl_map_flags = 1;
+pub const CL_MAP_WRITE: cl_map_flags = 2;
+pub const CL_MAP_WRITE_INVALIDATE_REGION: cl_map_flags = 4;
 
 pub const CL_PROGRAM_REFERENCE_COUNT: cl_program_info = 4448;
 pub const CL_PROGRAM_CONTEXT: cl_program_info = 4449;
@@ -320,74 +320,74 @@ pub const CL_BUILD_NONE: cl_build_status = -1;
 pub const CL_BUILD_ERROR: cl_build_status = -2;
 pub const CL_BUILD_IN_PROGRESS: cl_build_status = -3;
 
-pub const CL_KERNEL_FUNCTION_NAME: cl_int = 4496;
-pub const CL_KERNEL_NUM_ARGS: cl_int = 4497;
-pub const CL_KERNEL_REFERENCE_COUNT: cl_int = 4498;
-pub const CL_KERNEL_CONTEXT: cl_int = 4499;
-pub const CL_KERNEL_PROGRAM: cl_int = 4500;
-pub const CL_KERNEL_ATTRIBUTES: cl_int = 4501;
-pub const CL_KERNEL_ARG_ADDRESS_QUALIFIER: cl_int = 4502;
-pub const CL_KERNEL_ARG_ACCESS_QUALIFIER: cl_int = 4503;
-pub const CL_KERNEL_ARG_TYPE_NAME: cl_int = 4504;
-pub const CL_KERNEL_ARG_TYPE_QUALIFIER: cl_int = 4505;
-pub const CL_KERNEL_ARG_NAME: cl_int = 4506;
-pub const CL_KERNEL_ARG_ADDRESS_GLOBAL: cl_int = 4507;
-pub const CL_KERNEL_ARG_ADDRESS_LOCAL: cl_int = 4508;
-pub const CL_KERNEL_ARG_ADDRESS_CONSTANT: cl_int = 4509;
-pub const CL_KERNEL_ARG_ADDRESS_PRIVATE: cl_int = 4510;
-pub const CL_KERNEL_ARG_ACCESS_READ_ONLY: cl_int = 4512;
-pub const CL_KERNEL_ARG_ACCESS_WRITE_ONLY: cl_int = 4513;
-pub const CL_KERNEL_ARG_ACCESS_READ_WRITE: cl_int = 4514;
-pub const CL_KERNEL_ARG_ACCESS_NONE: cl_int = 4515;
-pub const CL_KERNEL_ARG_TYPE_NONE: cl_int = 0;
-pub const CL_KERNEL_ARG_TYPE_CONST: cl_int = 1;
-pub const CL_KERNEL_ARG_TYPE_RESTRICT: cl_int = 2;
-pub const CL_KERNEL_ARG_TYPE_VOLATILE: cl_int = 4;
-pub const CL_KERNEL_WORK_GROUP_SIZE: cl_int = 4528;
-pub const CL_KERNEL_COMPILE_WORK_GROUP_SIZE: cl_int = 4529;
-pub const CL_KERNEL_LOCAL_MEM_SIZE: cl_int = 4530;
-pub const CL_KERNEL_PREFERRED_WORK_GROUP_SIZE_MULTIPLE: cl_int = 4531;
-pub const CL_KERNEL_PRIVATE_MEM_SIZE: cl_int = 4532;
-pub const CL_KERNEL_GLOBAL_WORK_SIZE: cl_int = 4533;
-pub const CL_EVENT_COMMAND_QUEUE: cl_int = 4560;
-pub const CL_EVENT_COMMAND_TYPE: cl_int = 4561;
-pub const CL_EVENT_REFERENCE_COUNT: cl_int = 4562;
-pub const CL_EVENT_COMMAND_EXECUTION_STATUS: cl_int = 4563;
-pub const CL_EVENT_CONTEXT: cl_int = 4564;
-pub const CL_COMMAND_NDRANGE_KERNEL: cl_int = 4592;
-pub const CL_COMMAND_TASK: cl_int = 4593;
-pub const CL_COMMAND_NATIVE_KERNEL: cl_int = 4594;
-pub const CL_COMMAND_READ_BUFFER: cl_int = 4595;
-pub const CL_COMMAND_WRITE_BUFFER: cl_int = 4596;
-pub const CL_COMMAND_COPY_BUFFER: cl_int = 4597;
-pub const CL_COMMAND_READ_IMAGE: cl_int = 4598;
-pub const CL_COMMAND_WRITE_IMAGE: cl_int = 4599;
-pub const CL_COMMAND_COPY_IMAGE: cl_int = 4600;
-pub const CL_COMMAND_COPY_IMAGE_TO_BUFFER: cl_int = 4601;
-pub const CL_COMMAND_COPY_BUFFER_TO_IMAGE: cl_int = 4602;
-pub const CL_COMMAND_MAP_BUFFER: cl_int = 4603;
-pub const CL_COMMAND_MAP_IMAGE: cl_int = 4604;
-pub const CL_COMMAND_UNMAP_MEM_OBJECT: cl_int = 4605;
-pub const CL_COMMAND_MARKER: cl_int = 4606;
-pub const CL_COMMAND_ACQUIRE_GL_OBJECTS: cl_int = 4607;
-pub const CL_COMMAND_RELEASE_GL_OBJECTS: cl_int = 4608;
-pub const CL_COMMAND_READ_BUFFER_RECT: cl_int = 4609;
-pub const CL_COMMAND_WRITE_BUFFER_RECT: cl_int = 4610;
-pub const CL_COMMAND_COPY_BUFFER_RECT: cl_int = 4611;
-pub const CL_COMMAND_USER: cl_int = 4612;
-pub const CL_COMMAND_BARRIER: cl_int = 4613;
-pub const CL_COMMAND_MIGRATE_MEM_OBJECTS: cl_int = 4614;
-pub const CL_COMMAND_FILL_BUFFER: cl_int = 4615;
-pub const CL_COMMAND_FILL_IMAGE: cl_int = 4616;
+pub const CL_KERNEL_FUNCTION_NAME: cl_kernel_info = 4496;
+pub const CL_KERNEL_NUM_ARGS: cl_kernel_info = 4497;
+pub const CL_KERNEL_REFERENCE_COUNT: cl_kernel_info = 4498;
+pub const CL_KERNEL_CONTEXT: cl_kernel_info = 4499;
+pub const CL_KERNEL_PROGRAM: cl_kernel_info = 4500;
+pub const CL_KERNEL_ATTRIBUTES: cl_kernel_info = 4501;
+pub const CL_KERNEL_ARG_ADDRESS_QUALIFIER: cl_kernel_arg_info = 4502;
+pub const CL_KERNEL_ARG_ACCESS_QUALIFIER: cl_kernel_arg_info = 4503;
+pub const CL_KERNEL_ARG_TYPE_NAME: cl_kernel_arg_info = 4504;
+pub const CL_KERNEL_ARG_TYPE_QUALIFIER: cl_kernel_arg_info = 4505;
+pub const CL_KERNEL_ARG_NAME: cl_kernel_arg_info = 4506;
+pub const CL_KERNEL_ARG_ADDRESS_GLOBAL: cl_kernel_arg_address_qualifier = 4507;
+pub const CL_KERNEL_ARG_ADDRESS_LOCAL: cl_kernel_arg_address_qualifier = 4508;
+pub const CL_KERNEL_ARG_ADDRESS_CONSTANT: cl_kernel_arg_address_qualifier = 4509;
+pub const CL_KERNEL_ARG_ADDRESS_PRIVATE: cl_kernel_arg_address_qualifier = 4510;
+pub const CL_KERNEL_ARG_ACCESS_READ_ONLY: cl_kernel_arg_access_qualifier = 4512;
+pub const CL_KERNEL_ARG_ACCESS_WRITE_ONLY: cl_kernel_arg_access_qualifier = 4513;
+pub const CL_KERNEL_ARG_ACCESS_READ_WRITE: cl_kernel_arg_access_qualifier = 4514;
+pub const CL_KERNEL_ARG_ACCESS_NONE: cl_kernel_arg_access_qualifier = 4515;
+pub const CL_KERNEL_ARG_TYPE_NONE: cl_kernel_arg_type_qualifier = 0;
+pub const CL_KERNEL_ARG_TYPE_CONST: cl_kernel_arg_type_qualifier = 1;
+pub const CL_KERNEL_ARG_TYPE_RESTRICT: cl_kernel_arg_type_qualifier = 2;
+pub const CL_KERNEL_ARG_TYPE_VOLATILE: cl_kernel_arg_type_qualifier = 4;
+pub const CL_KERNEL_WORK_GROUP_SIZE: cl_kernel_work_group_info = 4528;
+pub const CL_KERNEL_COMPILE_WORK_GROUP_SIZE: cl_kernel_work_group_info = 4529;
+pub const CL_KERNEL_LOCAL_MEM_SIZE: cl_kernel_work_group_info = 4530;
+pub const CL_KERNEL_PREFERRED_WORK_GROUP_SIZE_MULTIPLE: cl_kernel_work_group_info = 4531;
+pub const CL_KERNEL_PRIVATE_MEM_SIZE: cl_kernel_work_group_info = 4532;
+pub const CL_KERNEL_GLOBAL_WORK_SIZE: cl_kernel_work_group_info = 4533;
+pub const CL_EVENT_COMMAND_QUEUE: cl_event_info = 4560;
+pub const CL_EVENT_COMMAND_TYPE: cl_event_info = 4561;
+pub const CL_EVENT_REFERENCE_COUNT: cl_event_info = 4562;
+pub const CL_EVENT_COMMAND_EXECUTION_STATUS: cl_event_info = 4563;
+pub const CL_EVENT_CONTEXT: cl_event_info = 4564;
+pub const CL_COMMAND_NDRANGE_KERNEL: cl_command_type = 4592;
+pub const CL_COMMAND_TASK: cl_command_type = 4593;
+pub const CL_COMMAND_NATIVE_KERNEL: cl_command_type = 4594;
+pub const CL_COMMAND_READ_BUFFER: cl_command_type = 4595;
+pub const CL_COMMAND_WRITE_BUFFER: cl_command_type = 4596;
+pub const CL_COMMAND_COPY_BUFFER: cl_command_type = 4597;
+pub const CL_COMMAND_READ_IMAGE: cl_command_type = 4598;
+pub const CL_COMMAND_WRITE_IMAGE: cl_command_type = 4599;
+pub const CL_COMMAND_COPY_IMAGE: cl_command_type = 4600;
+pub const CL_COMMAND_COPY_IMAGE_TO_BUFFER: cl_command_type = 4601;
+pub const CL_COMMAND_COPY_BUFFER_TO_IMAGE: cl_command_type = 4602;
+pub const CL_COMMAND_MAP_BUFFER: cl_command_type = 4603;
+pub const CL_COMMAND_MAP_IMAGE: cl_command_type = 4604;
+pub const CL_COMMAND_UNMAP_MEM_OBJECT: cl_command_type = 4605;
+pub const CL_COMMAND_MARKER: cl_command_type = 4606;
+pub const CL_COMMAND_ACQUIRE_GL_OBJECTS: cl_command_type = 4607;
+pub const CL_COMMAND_RELEASE_GL_OBJECTS: cl_command_type = 4608;
+pub const CL_COMMAND_READ_BUFFER_RECT: cl_command_type = 4609;
+pub const CL_COMMAND_WRITE_BUFFER_RECT: cl_command_type = 4610;
+pub const CL_COMMAND_COPY_BUFFER_RECT: cl_command_type = 4611;
+pub const CL_COMMAND_USER: cl_command_type = 4612;
+pub const CL_COMMAND_BARRIER: cl_command_type = 4613;
+pub const CL_COMMAND_MIGRATE_MEM_OBJECTS: cl_command_type = 4614;
+pub const CL_COMMAND_FILL_BUFFER: cl_command_type = 4615;
+pub const CL_COMMAND_FILL_IMAGE: cl_command_type = 4616;
 pub const CL_COMPLETE: cl_int = 0;
 pub const CL_RUNNING: cl_int = 1;
 pub const CL_SUBMITTED: cl_int = 2;
 pub const CL_QUEUED: cl_int = 3;
 pub const CL_BUFFER_CREATE_TYPE_REGION: cl_int = 4640;
-pub const CL_PROFILING_COMMAND_QUEUED: cl_int = 4736;
-pub const CL_PROFILING_COMMAND_SUBMIT: cl_int = 4737;
-pub const CL_PROFILING_COMMAND_START: cl_int = 4738;
-pub const CL_PROFILING_COMMAND_END: cl_int = 4739;
+pub const CL_PROFILING_COMMAND_QUEUED: cl_profiling_info = 4736;
+pub const CL_PROFILING_COMMAND_SUBMIT: cl_profiling_info = 4737;
+pub const CL_PROFILING_COMMAND_START: cl_profiling_info = 4738;
+pub const CL_PROFILING_COMMAND_END: cl_profiling_info = 4739;
 
 pub type cl_char = i8;
 pub type cl_uchar = u8;
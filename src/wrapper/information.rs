@@ -169,6 +169,8 @@ result_impl!(isize);
 result_impl!(ffi::cl_platform_id);
 result_impl!(ffi::cl_device_id);
 result_impl!(ffi::cl_context);
+result_impl!(ffi::cl_program);
+result_impl!(ffi::cl_command_queue);
 
 /// A trait describing a piece of information.
 pub trait Information<T> {
@@ -0,0 +1,167 @@
+//! Bookkeeping of the memory allocated through the crate, per context and per device. Memory
+//! objects constructors reserve their size through `reserve` before calling the driver, and
+//! give it back through `release` once the runtime has actually freed the memory object.
+//!
+//! The account of a context is dropped once neither a `Context` handle to it nor a memory
+//! object allocated in it is alive anymore, so that a context created later at the same
+//! address starts afresh. `Context` handles are counted here rather than through the reference
+//! count of the runtime, which other OpenCL objects also hold.
+
+use wrapper::types::context::{self, Context, MemoryUsage};
+use wrapper::types::device::Device;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+// Handles are stored as `usize` so that the registry is `Send`.
+struct Account {
+    usage: MemoryUsage,
+    devices: Vec<usize>,
+}
+
+struct Registry {
+    contexts: BTreeMap<usize, Account>,
+    devices: BTreeMap<usize, MemoryUsage>,
+
+    // Number of live `Context` handles per context.
+    handles: BTreeMap<usize, usize>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    contexts: BTreeMap::new(),
+    devices: BTreeMap::new(),
+    handles: BTreeMap::new(),
+});
+
+fn key(context: &Context) -> usize {
+    unsafe { context.underlying() as usize }
+}
+
+impl Registry {
+    fn account(&mut self, context: &Context) -> &mut Account {
+        self.contexts.entry(key(context)).or_insert_with(|| {
+            let devices = context.get_info::<context::information::Devices>();
+            Account {
+                usage: MemoryUsage::default(),
+                devices: devices.iter().map(|d| unsafe { d.underlying() as usize }).collect(),
+            }
+        })
+    }
+
+    // Drop the account of a context if neither a handle nor a memory object is alive anymore.
+    fn collect(&mut self, context: usize) {
+        if self.handles.contains_key(&context) {
+            return;
+        }
+
+        let unused = match self.contexts.get(&context) {
+            Some(account) => account.usage.objects() == 0,
+            None => false,
+        };
+        if unused {
+            self.contexts.remove(&context);
+        }
+    }
+}
+
+/// Reserve `size` bytes in `context`, unless this would exceed the budget of the context.
+/// Return `true` on success.
+pub(super) fn reserve(context: &Context, size: usize) -> bool {
+    let mut registry = REGISTRY.lock().unwrap();
+    let devices = {
+        let account = registry.account(context);
+        if !account.usage.reserve(size) {
+            return false;
+        }
+        account.devices.clone()
+    };
+
+    for device in devices {
+        registry.devices.entry(device).or_default().reserve(size);
+    }
+    true
+}
+
+/// Give back `size` bytes previously reserved in `context` through `reserve`. This is called
+/// from a runtime callback, hence `context` is given as a raw key.
+pub(super) fn release(context: usize, size: usize) {
+    let mut registry = REGISTRY.lock().unwrap();
+    let devices = match registry.contexts.get_mut(&context) {
+        Some(account) => {
+            account.usage.release(size);
+            account.devices.clone()
+        }
+
+        // The account was never created.
+        None => return,
+    };
+
+    for device in devices {
+        if let Some(usage) = registry.devices.get_mut(&device) {
+            usage.release(size);
+        }
+    }
+    registry.collect(context);
+}
+
+pub(super) fn usage(context: &Context) -> MemoryUsage {
+    REGISTRY.lock().unwrap().contexts.get(&key(context)).map(|a| a.usage).unwrap_or_default()
+}
+
+pub(super) fn device_usage(device: &Device) -> MemoryUsage {
+    let key = unsafe { device.underlying() as usize };
+    REGISTRY.lock().unwrap().devices.get(&key).cloned().unwrap_or_default()
+}
+
+pub(super) fn set_budget(context: &Context, budget: Option<usize>) {
+    REGISTRY.lock().unwrap().account(context).usage.set_budget(budget);
+}
+
+/// Record that a new `Context` handle to `context` is alive.
+pub(super) fn retain_handle(context: &Context) {
+    *REGISTRY.lock().unwrap().handles.entry(key(context)).or_insert(0) += 1;
+}
+
+/// Record that a `Context` handle to `context` is dropped.
+pub(super) fn release_handle(context: &Context) {
+    let mut registry = REGISTRY.lock().unwrap();
+    let key = key(context);
+    let remaining = match registry.handles.get_mut(&key) {
+        Some(handles) => {
+            *handles -= 1;
+            *handles
+        }
+        None => 0,
+    };
+
+    if remaining == 0 {
+        registry.handles.remove(&key);
+        registry.collect(key);
+    }
+}
+
+#[test]
+fn test_account_lifetime() {
+    use wrapper::types::mem::{self, Buffer};
+    use std::time::{Duration, Instant};
+
+    let registered = |key| REGISTRY.lock().unwrap().contexts.contains_key(&key);
+    let context = Context::default().unwrap();
+    let key = key(&context);
+
+    set_budget(&context, Some(1024));
+    let clone = context.clone();
+    drop(context);
+    assert!(registered(key));
+
+    // The account outlives the last handle as long as a memory object is alive.
+    let buffer = Buffer::create(vec![0u8; 16], &clone, mem::Flags::new()).unwrap();
+    drop(clone);
+    assert!(registered(key));
+
+    drop(buffer);
+    let start = Instant::now();
+    while registered(key) {
+        assert!(start.elapsed() < Duration::from_secs(5), "the account was not dropped");
+        ::std::thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,465 @@
+//! A module defining `CommandList`, a list of commands recorded once and replayed many times.
+
+use wrapper::ffi;
+use wrapper::types::context::Context;
+use wrapper::types::command_queue::{self, WaitList, NdRange, LaunchError, TransferError};
+use wrapper::types::kernel::{self, Kernel};
+use wrapper::types::kernel::arg::{KernelArg, SetArgError};
+use wrapper::types::kernel::typed::KernelArgs;
+use wrapper::types::mem::{self, Buffer};
+use wrapper::types::event::Event;
+use std::fmt;
+
+/// Identifies a kernel launch recorded in a `CommandList`, for overriding its arguments on
+/// replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LaunchId(usize);
+
+// Enqueue a recorded memory transfer.
+type Enqueue = Box<dyn Fn(WaitList) -> Result<Event, TransferError> + Send>;
+
+// Set the recorded arguments on a kernel.
+type SetArgs = Box<dyn Fn(&mut Kernel) -> Result<(), (ffi::cl_uint, SetArgError)> + Send>;
+
+// A recorded command.
+enum Command {
+    Transfer(Enqueue),
+    Launch {
+        kernel: Kernel,
+        range: NdRange,
+        // Set the recorded arguments back after an override.
+        restore: SetArgs,
+    },
+    Barrier,
+}
+
+/// An error returned by `CommandList::replay`. Commands are identified by their index in the
+/// list.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ReplayError {
+    /// The command queue is not associated with the context of the list.
+    InvalidContext,
+
+    /// The wait list is invalid or one of its events failed (only for an empty list).
+    WaitList(command_queue::WaitListError),
+
+    /// The kernel launch at index `index` failed.
+    Launch {
+        /// Index of the command in the list.
+        index: usize,
+
+        /// The error returned by the launch.
+        error: LaunchError,
+    },
+
+    /// The memory transfer at index `index` failed.
+    Transfer {
+        /// Index of the command in the list.
+        index: usize,
+
+        /// The error returned by the transfer.
+        error: TransferError,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::InvalidContext =>
+                write!(f, "the command list and the command queue have different contexts"),
+            ReplayError::WaitList(err) =>
+                write!(f, "{}", err),
+            ReplayError::Launch { index, ref error } =>
+                write!(f, "command {}: {}", index, error),
+            ReplayError::Transfer { index, error } =>
+                write!(f, "command {}: {}", index, error),
+        }
+    }
+}
+
+/// Arguments overriding the recorded arguments of some kernel launches for a single replay,
+/// see `CommandList::replay_with`.
+#[derive(Clone, Default)]
+pub struct Overrides<'a> {
+    args: Vec<(LaunchId, ffi::cl_uint, &'a dyn KernelArg)>,
+}
+
+impl<'a> Overrides<'a> {
+    /// Create an empty set of overrides.
+    pub fn new() -> Self {
+        Overrides {
+            args: Vec::new(),
+        }
+    }
+
+    /// Override the argument at index `index` of the kernel launch `launch` with `arg`.
+    pub fn set<A: KernelArg>(mut self, launch: LaunchId, index: ffi::cl_uint, arg: &'a A) -> Self {
+        self.args.push((launch, index, arg));
+        self
+    }
+}
+
+impl<'a> fmt::Debug for Overrides<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+         .entries(self.args.iter().map(|&(launch, index, arg)| (launch, index, arg.kind())))
+         .finish()
+    }
+}
+
+/// A list of commands (memory transfers, kernel launches and barriers) recorded once and
+/// replayed many times on command queues of the same context.
+///
+/// Everything which can be checked on the host is checked when recording, and the arguments of
+/// each kernel launch are set once on a dedicated kernel (see `Kernel::duplicate`), so that a
+/// replay only submits the commands to the driver. Each replayed command starts after the
+/// previous one has completed, including on out-of-order command queues.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{CommandQueue, Buffer, program, mem, Future};
+/// use gprust::command_queue::{self, NdRange};
+/// use gprust::command_list::{CommandList, Overrides};
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let queue = CommandQueue::default().ok_or("no default command queue")?;
+/// let context = queue.get_info::<command_queue::information::Context>();
+/// let program = program::Builder::create_with_sources(
+///     Some("__kernel void scale(__global float * data, float factor) {
+///         data[get_global_id(0)] *= factor;
+///     }"),
+///     &context
+/// ).expect("I did provide a source");
+/// let program = program.build().wait().map_err(|_| "build failed")?;
+/// let kernel = program.create_kernel("scale").map_err(|_| "no such kernel")?;
+/// let data = Buffer::create(vec![0.0f32; 16], &context, mem::Flags::new())
+///     .map_err(|_| "failed to create buffer")?;
+///
+/// let mut list = CommandList::new(&context);
+/// list.write(&data, 0, &[1.0; 16]).map_err(|_| "invalid write")?;
+/// let scale = list.launch(&kernel, &NdRange::new(&[16]), (data.clone(), 2.0f32))
+///                 .map_err(|_| "invalid launch")?;
+///
+/// for frame in 0..10 {
+///     let factor = frame as f32;
+///     let overrides = Overrides::new().set(scale, 1, &factor);
+///     list.replay_with(&queue, &overrides).map_err(|_| "replay failed")?
+///         .wait()
+///         .map_err(|_| "command failed")?;
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+pub struct CommandList {
+    context: Context,
+    commands: Vec<Command>,
+}
+
+impl CommandList {
+    /// Create an empty list for command queues associated with `context`.
+    pub fn new(context: &Context) -> Self {
+        CommandList {
+            context: context.clone(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Return the number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Return `true` if no command has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Record a write of `data` to `buffer` starting at element `offset`. `data` is copied
+    /// into the list.
+    ///
+    /// # Errors
+    /// * `TransferError::OutOfBounds` if the written range exceeds the size of `buffer`.
+    /// * `TransferError::InvalidContext` if `buffer` is associated with another context.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn write<T>(&mut self, buffer: &Buffer<T>, offset: usize, data: &[T])
+        -> Result<(), TransferError>
+        where T: Copy + Send + 'static
+    {
+        self.check_buffer(buffer)?;
+        command_queue::check_bounds(buffer, offset, data.len())?;
+
+        let (buffer, data) = (buffer.clone(), data.to_vec());
+        self.commands.push(Command::Transfer(Box::new(move |queue| {
+            queue.enqueue_write_buffer(&buffer, offset, &data)
+        })));
+        Ok(())
+    }
+
+    /// Record a copy of `len` elements of `src` starting at element `src_offset` to `dst`
+    /// starting at element `dst_offset`.
+    ///
+    /// # Errors
+    /// * `TransferError::Overlap` if `src` and `dst` are the same buffer and the source and
+    /// destination ranges overlap.
+    /// * Same as `CommandList::write` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandList::write`.
+    pub fn copy<T>(&mut self, src: &Buffer<T>, src_offset: usize, dst: &Buffer<T>,
+                   dst_offset: usize, len: usize)
+        -> Result<(), TransferError>
+        where T: Send + 'static
+    {
+        self.check_buffer(src)?;
+        self.check_buffer(dst)?;
+        command_queue::check_bounds(src, src_offset, len)?;
+        command_queue::check_bounds(dst, dst_offset, len)?;
+        if src == dst && src_offset < dst_offset + len && dst_offset < src_offset + len {
+            return Err(TransferError::Overlap);
+        }
+
+        let (src, dst) = (src.clone(), dst.clone());
+        self.commands.push(Command::Transfer(Box::new(move |queue| {
+            queue.enqueue_copy_buffer(&src, src_offset, &dst, dst_offset, len)
+        })));
+        Ok(())
+    }
+
+    /// Record a launch of `kernel` over `range` with the arguments `args`. The arguments are
+    /// set and validated right away on a duplicate of `kernel` (see `Kernel::enable_validation`)
+    /// so that `kernel` itself is left untouched, and are kept alive by the list.
+    ///
+    /// Return an identifier of the launch, for overriding its arguments with
+    /// `CommandList::replay_with`.
+    ///
+    /// # Errors
+    /// * `LaunchError::InvalidContext` if `kernel` is associated with another context.
+    /// * `LaunchError::InvalidWorkDimension` if the dimensions of `range` are invalid.
+    /// * `LaunchError::InvalidArg { .. }` if one of the arguments was rejected by the driver.
+    /// * `LaunchError::InvalidArgs(errors)` if some arguments are missing or, when validation
+    /// is enabled on `kernel`, do not match the kernel parameters.
+    ///
+    /// # Panics
+    /// Same as `Kernel::duplicate`.
+    pub fn launch<A>(&mut self, kernel: &Kernel, range: &NdRange, args: A)
+        -> Result<LaunchId, LaunchError>
+        where A: KernelArgs + Send + 'static
+    {
+        if kernel.get_info::<kernel::information::Context>() != self.context {
+            return Err(LaunchError::InvalidContext);
+        }
+
+        if !range.is_valid() {
+            return Err(LaunchError::InvalidWorkDimension);
+        }
+
+        let mut recorded = kernel.duplicate();
+        args.set(&mut recorded).map_err(|(index, error)| LaunchError::InvalidArg { index, error })?;
+        recorded.validate_args().map_err(LaunchError::InvalidArgs)?;
+
+        self.commands.push(Command::Launch {
+            kernel: recorded,
+            range: range.clone(),
+            restore: Box::new(move |kernel| args.set(kernel)),
+        });
+        Ok(LaunchId(self.commands.len() - 1))
+    }
+
+    /// Record a barrier. Since replayed commands already wait for each other, this is only
+    /// needed for ordering the list against commands enqueued concurrently on the same queue.
+    pub fn barrier(&mut self) {
+        self.commands.push(Command::Barrier);
+    }
+
+    /// Replay the recorded commands on `queue`, which is either a `&CommandQueue` or a
+    /// `WaitList` returned by `CommandQueue::after` (the first command then waits for its
+    /// events). Return the event of the last command.
+    ///
+    /// # Errors
+    /// * `ReplayError::InvalidContext` if `queue` is associated with another context.
+    /// * `ReplayError::Launch { .. }` or `ReplayError::Transfer { .. }` if a command could
+    /// not be enqueued, the commands before it being enqueued anyway.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn replay<'a, Q: Into<WaitList<'a>>>(&mut self, queue: Q) -> Result<Event, ReplayError> {
+        self.replay_with(queue, &Overrides::new())
+    }
+
+    /// Same as `CommandList::replay`, overriding the arguments of some kernel launches with
+    /// `overrides` for this replay only. The overriding arguments are checked against the
+    /// kernel parameters if validation was enabled on the recorded kernel.
+    ///
+    /// # Errors
+    /// * `ReplayError::Launch { error: LaunchError::InvalidArg { .. }, .. }` or
+    /// `ReplayError::Launch { error: LaunchError::InvalidArgs(..), .. }` if an overriding
+    /// argument is invalid.
+    /// * Same as `CommandList::replay` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandList::replay`, or if `overrides` refers to a launch which was not
+    /// recorded in this list.
+    pub fn replay_with<'a, Q: Into<WaitList<'a>>>(&mut self, queue: Q, overrides: &Overrides)
+        -> Result<Event, ReplayError>
+    {
+        let wait_list = queue.into();
+        let queue = wait_list.queue();
+        if queue.get_info::<command_queue::information::Context>() != self.context {
+            return Err(ReplayError::InvalidContext);
+        }
+
+        for &(LaunchId(index), _, _) in &overrides.args {
+            match self.commands.get(index) {
+                Some(&Command::Launch { .. }) => (),
+                _ => panic!("no kernel launch at index {} in the command list", index),
+            }
+        }
+
+        let out_of_order = queue.get_info::<command_queue::information::Properties>()
+                                .out_of_order_exec();
+        let mut last: Option<Event> = None;
+
+        for (index, command) in self.commands.iter_mut().enumerate() {
+            let previous;
+            let after = match last {
+                None => wait_list,
+                Some(ref event) if out_of_order => {
+                    previous = [event];
+                    queue.after(&previous)
+                },
+                Some(_) => queue.after(&[]),
+            };
+
+            let event = match *command {
+                Command::Transfer(ref enqueue) =>
+                    enqueue(after).map_err(|error| ReplayError::Transfer { index, error })?,
+                Command::Launch { ref mut kernel, ref range, ref restore } => {
+                    let launch_error = |error| ReplayError::Launch { index, error };
+                    let mut overridden = false;
+                    let mut result = Ok(());
+
+                    for &(LaunchId(launch), arg_index, arg) in &overrides.args {
+                        if launch == index {
+                            overridden = true;
+                            result = kernel.set_arg(arg_index, arg).map_err(|error| {
+                                LaunchError::InvalidArg { index: arg_index, error }
+                            });
+                            if result.is_err() {
+                                break;
+                            }
+                        }
+                    }
+
+                    let result = result.and_then(|()| if overridden {
+                        kernel.validate_args()
+                              .map_err(LaunchError::InvalidArgs)
+                              .and_then(|()| after.enqueue_nd_range_unchecked(kernel, range))
+                    } else {
+                        after.enqueue_nd_range_unchecked(kernel, range)
+                    });
+
+                    // Even if an override was rejected, the previous ones have been applied.
+                    if overridden {
+                        restore(kernel).map_err(|(index, error)| {
+                            launch_error(LaunchError::InvalidArg { index, error })
+                        })?;
+                    }
+                    result.map_err(launch_error)?
+                },
+                Command::Barrier =>
+                    after.enqueue_barrier().map_err(|error| {
+                        ReplayError::Transfer { index, error: TransferError::WaitList(error) }
+                    })?,
+            };
+
+            last = Some(event);
+        }
+
+        match last {
+            Some(event) => Ok(event),
+            None => wait_list.enqueue_marker().map_err(ReplayError::WaitList),
+        }
+    }
+
+    // Check that `buffer` is associated with the context of the list.
+    fn check_buffer<T>(&self, buffer: &Buffer<T>) -> Result<(), TransferError> {
+        if buffer.get_info::<mem::information::Context>() == self.context {
+            Ok(())
+        } else {
+            Err(TransferError::InvalidContext)
+        }
+    }
+}
+
+impl fmt::Debug for CommandList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CommandList")
+         .field("context", &self.context)
+         .field("len", &self.commands.len())
+         .finish()
+    }
+}
+
+#[test]
+fn test_replay() {
+    use wrapper::types::{command_queue::CommandQueue, program};
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void add(__global int * data, int value) {
+            data[get_global_id(0)] += value;
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+    let kernel = program.create_kernel("add").unwrap();
+    let data = Buffer::create(vec![0i32; 8], &context, mem::Flags::new()).unwrap();
+    let copy = Buffer::create(vec![0i32; 8], &context, mem::Flags::new()).unwrap();
+    let range = NdRange::new(&[8]);
+
+    let mut list = CommandList::new(&context);
+    list.write(&data, 0, &[1; 8]).unwrap();
+    let add = list.launch(&kernel, &range, (data.clone(), 2i32)).unwrap();
+    list.barrier();
+    list.copy(&data, 0, &copy, 0, 8).unwrap();
+    assert_eq!(list.len(), 4);
+
+    assert_eq!(list.write(&data, 4, &[0; 8]), Err(TransferError::OutOfBounds));
+    assert_eq!(list.copy(&data, 0, &data, 4, 5), Err(TransferError::Overlap));
+    match list.launch(&kernel, &range, (data.clone(),)) {
+        Err(LaunchError::InvalidArgs(err)) =>
+            assert_eq!(err.errors(), &[kernel::arg::ArgError::Unset { index: 1, name: None }]),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut result = [0; 8];
+    list.replay(&queue).unwrap().wait().unwrap();
+    queue.enqueue_read_buffer(&copy, 0, &mut result).unwrap();
+    assert_eq!(result, [3; 8]);
+
+    list.replay_with(&queue, &Overrides::new().set(add, 1, &10i32)).unwrap().wait().unwrap();
+    queue.enqueue_read_buffer(&copy, 0, &mut result).unwrap();
+    assert_eq!(result, [11; 8]);
+
+    // The recorded argument is restored after an override.
+    list.replay(&queue).unwrap().wait().unwrap();
+    queue.enqueue_read_buffer(&copy, 0, &mut result).unwrap();
+    assert_eq!(result, [3; 8]);
+
+    // Including when a later override is rejected.
+    let overrides = Overrides::new().set(add, 1, &10i32).set(add, 2, &10i32);
+    match list.replay_with(&queue, &overrides) {
+        Err(ReplayError::Launch { index: 1, error: LaunchError::InvalidArg { index: 2, .. } }) =>
+            (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    list.replay(&queue).unwrap().wait().unwrap();
+    queue.enqueue_read_buffer(&copy, 0, &mut result).unwrap();
+    assert_eq!(result, [3; 8]);
+}
@@ -2,10 +2,16 @@
 
 use wrapper::ffi;
 use wrapper::types::context::Context;
-use wrapper::types::device::Device;
+use wrapper::types::device::{self, Device};
+use wrapper::types::kernel::{self, Kernel};
+use wrapper::types::kernel::arg::Scalar;
+use wrapper::types::event::{self, Event};
+use wrapper::types::mem::{self as cl_mem, Buffer, MemObject};
 use wrapper::information::InformationResult;
 use errors::*;
-use std::fmt;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{fmt, mem, panic, ptr, slice};
 
 pub mod information {
     //! A module containing the information marker types for `CommandQueue`.
@@ -43,13 +49,26 @@ bitfield_builder!(
     [profiling, "profiling"] => ffi::CL_QUEUE_PROFILING_ENABLE
 );
 
+bitfield_builder!(
+    [MigrationFlags, MigrationFlagsBuilder, "MigrationFlags"],
+    "cl_mem_migration_flags",
+    [host, "host"] => ffi::CL_MIGRATE_MEM_OBJECT_HOST,
+    [content_undefined, "content_undefined"] => ffi::CL_MIGRATE_MEM_OBJECT_CONTENT_UNDEFINED
+);
+
 /// `CommandQueue` is a high-level type which maps to the low-level `cl_command_queue` OpenCL type.
 /// An object of type `CommandQueue` acts as a ref-counted reference to an OpenCL command queue.
-#[derive(PartialEq, Eq)]
 pub struct CommandQueue {
     queue: ffi::cl_command_queue,
+
+    // One of `SYNC_UNKNOWN`, `SYNC_UNSUPPORTED` or `SYNC_SUPPORTED`, see `supports_wait_list_sync`.
+    wait_list_sync: AtomicUsize,
 }
 
+const SYNC_UNKNOWN: usize = 0;
+const SYNC_UNSUPPORTED: usize = 1;
+const SYNC_SUPPORTED: usize = 2;
+
 unsafe impl Send for CommandQueue { }
 unsafe impl Sync for CommandQueue { }
 
@@ -75,7 +94,451 @@ impl fmt::Display for CreationError {
     }
 }
 
+/// An n-dimensional range of work-items over which a kernel is executed, made of a global size
+/// and optionally of a local size (i.e. the work-group size) and of a global offset.
+/// All three must have the same number of dimensions, between 1 and 3.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NdRange {
+    global_field: Vec<usize>,
+    local_field: Option<Vec<usize>>,
+    offset_field: Option<Vec<usize>>,
+}
+
+impl NdRange {
+    /// Create a range with `global` as the global size. The local size is left to the
+    /// implementation.
+    pub fn new(global: &[usize]) -> Self {
+        NdRange {
+            global_field: global.to_vec(),
+            local_field: None,
+            offset_field: None,
+        }
+    }
+
+    /// Return the global size.
+    pub fn global(&self) -> &[usize] {
+        &self.global_field
+    }
+
+    /// Return the local size if any.
+    pub fn local(&self) -> Option<&[usize]> {
+        self.local_field.as_deref()
+    }
+
+    /// Set the local size.
+    pub fn set_local(mut self, local: &[usize]) -> Self {
+        self.local_field = Some(local.to_vec());
+        self
+    }
+
+    /// Return the global offset if any.
+    pub fn offset(&self) -> Option<&[usize]> {
+        self.offset_field.as_deref()
+    }
+
+    /// Set the global offset.
+    pub fn set_offset(mut self, offset: &[usize]) -> Self {
+        self.offset_field = Some(offset.to_vec());
+        self
+    }
+
+    /// Return the number of dimensions of the range.
+    pub fn dimensions(&self) -> usize {
+        self.global_field.len()
+    }
+
+    pub(super) fn is_valid(&self) -> bool {
+        let dimensions = self.dimensions();
+        let same = |sizes: &Option<Vec<usize>>| match *sizes {
+            Some(ref sizes) => sizes.len() == dimensions,
+            None => true,
+        };
+
+        (1..=3).contains(&dimensions) && same(&self.local_field) && same(&self.offset_field)
+    }
+}
+
+/// A three-dimensional box of elements within a linear memory (a buffer or a host slice) seen
+/// as slices of rows, as used by the rectangular transfers such as
+/// `CommandQueue::enqueue_copy_buffer_rect`: the element at coordinates `(x, y, z)` lies at
+/// index `x + y * row_pitch + z * slice_pitch`. All quantities are in elements.
+///
+/// By default the origin is zero and the memory is tightly packed, i.e. the row pitch is the
+/// width of the box and the slice pitch is the row pitch times its height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region3 {
+    origin_field: [usize; 3],
+    size_field: [usize; 3],
+    row_pitch_field: Option<usize>,
+    slice_pitch_field: Option<usize>,
+}
+
+impl Region3 {
+    /// Create a region of width `size[0]`, height `size[1]` and depth `size[2]`.
+    pub fn new(size: [usize; 3]) -> Self {
+        Region3 {
+            origin_field: [0; 3],
+            size_field: size,
+            row_pitch_field: None,
+            slice_pitch_field: None,
+        }
+    }
+
+    /// Return the coordinates of the first element of the box.
+    pub fn origin(&self) -> [usize; 3] {
+        self.origin_field
+    }
+
+    /// Set the coordinates of the first element of the box.
+    pub fn set_origin(mut self, origin: [usize; 3]) -> Self {
+        self.origin_field = origin;
+        self
+    }
+
+    /// Return the size of the box.
+    pub fn size(&self) -> [usize; 3] {
+        self.size_field
+    }
+
+    /// Return the number of elements between the starts of two consecutive rows.
+    pub fn row_pitch(&self) -> usize {
+        self.row_pitch_field.unwrap_or(self.size_field[0])
+    }
+
+    /// Set the number of elements between the starts of two consecutive rows.
+    pub fn set_row_pitch(mut self, pitch: usize) -> Self {
+        self.row_pitch_field = Some(pitch);
+        self
+    }
+
+    /// Return the number of elements between the starts of two consecutive slices.
+    pub fn slice_pitch(&self) -> usize {
+        self.slice_pitch_field.unwrap_or(self.row_pitch() * self.size_field[1])
+    }
+
+    /// Set the number of elements between the starts of two consecutive slices.
+    pub fn set_slice_pitch(mut self, pitch: usize) -> Self {
+        self.slice_pitch_field = Some(pitch);
+        self
+    }
+
+    // A region is valid if it is not empty and rows and slices do not overlap. A default
+    // slice pitch which overflows is left to `end`.
+    fn is_valid(&self) -> bool {
+        let size = self.size_field;
+        let slice_len = self.row_pitch().checked_mul(size[1]);
+        let slices_ok = match (self.slice_pitch_field, slice_len) {
+            (Some(pitch), Some(slice_len)) => pitch >= slice_len,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        !size.contains(&0) && self.row_pitch() >= size[0] && slices_ok
+    }
+
+    // Return the index of the first element of each row, in increasing order, or
+    // `TransferError::OutOfBounds` if an index overflows.
+    fn rows(&self) -> Result<impl Iterator<Item = usize>, TransferError> {
+        // Rows start before the end of the box, hence they cannot overflow if the end does not.
+        self.end()?;
+
+        let (origin, size) = (self.origin_field, self.size_field);
+        let (row_pitch, slice_pitch) = (self.row_pitch(), self.slice_pitch());
+
+        Ok((0..size[2]).flat_map(move |z| (0..size[1]).map(move |y| {
+            origin[0] + (origin[1] + y) * row_pitch + (origin[2] + z) * slice_pitch
+        })))
+    }
+
+    // Return the index following the last element of a valid box, or
+    // `TransferError::OutOfBounds` if it overflows.
+    fn end(&self) -> Result<usize, TransferError> {
+        let (origin, size) = (self.origin_field, self.size_field);
+        let slice_pitch = match self.slice_pitch_field {
+            Some(pitch) => Some(pitch),
+            None => self.row_pitch().checked_mul(size[1]),
+        };
+
+        let last_row = origin[1].checked_add(size[1] - 1)
+                                .and_then(|y| y.checked_mul(self.row_pitch()));
+        let last_slice = origin[2].checked_add(size[2] - 1)
+                                  .and_then(|z| slice_pitch.and_then(|p| z.checked_mul(p)));
+
+        origin[0].checked_add(size[0])
+                 .and_then(|end| last_row.and_then(|row| end.checked_add(row)))
+                 .and_then(|end| last_slice.and_then(|slice| end.checked_add(slice)))
+                 .ok_or(TransferError::OutOfBounds)
+    }
+
+    // Convert the origin, the size and the pitches to the bytes expected by the driver.
+    fn origin_in_bytes(&self, element: usize) -> [usize; 3] {
+        [self.origin_field[0] * element, self.origin_field[1], self.origin_field[2]]
+    }
+
+    fn size_in_bytes(&self, element: usize) -> [usize; 3] {
+        [self.size_field[0] * element, self.size_field[1], self.size_field[2]]
+    }
+}
+
+// Return whether two valid regions of the same memory share an element, by walking their rows
+// in increasing order.
+fn overlap(a: &Region3, b: &Region3) -> Result<bool, TransferError> {
+    let (mut a_rows, mut b_rows) = (a.rows()?.peekable(), b.rows()?.peekable());
+    let (a_width, b_width) = (a.size()[0], b.size()[0]);
+
+    while let (Some(&a_row), Some(&b_row)) = (a_rows.peek(), b_rows.peek()) {
+        if a_row < b_row + b_width && b_row < a_row + a_width {
+            return Ok(true);
+        }
+
+        if a_row + a_width <= b_row + b_width {
+            a_rows.next();
+        } else {
+            b_rows.next();
+        }
+    }
+
+    Ok(false)
+}
+
+/// An error related to the wait list given through `CommandQueue::after`. Events are
+/// identified by their index in the wait list.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum WaitListError {
+    /// The event at index `index` is not associated with the context of the command queue.
+    ContextMismatch {
+        /// Index of the event in the wait list.
+        index: usize,
+    },
+
+    /// The command of the event at index `index` was abnormally terminated before the command
+    /// depending on it was enqueued, so that the latter could not be executed.
+    FailedDependency {
+        /// Index of the event in the wait list.
+        index: usize,
+
+        /// The negative execution status of the failed command.
+        status: ffi::cl_int,
+    },
+
+    /// The runtime reported a failed dependency, but no event of the wait list was abnormally
+    /// terminated: the failure comes from an earlier command of an in-order queue, or from a
+    /// dependency of one of the events.
+    FailedIndirectDependency,
+}
+
+impl fmt::Display for WaitListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WaitListError::ContextMismatch { index } =>
+                write!(f, "event {} of the wait list belongs to another context", index),
+            WaitListError::FailedDependency { index, status } =>
+                write!(f, "event {} of the wait list failed with execution status {}",
+                       index, status),
+            WaitListError::FailedIndirectDependency =>
+                write!(f, "a command the enqueued one depends on failed"),
+        }
+    }
+}
+
+/// An error returned by `CommandQueue::enqueue_nd_range` and `CommandQueue::enqueue_task`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum LaunchError {
+    /// The wait list is invalid or one of its events failed.
+    WaitList(WaitListError),
+
+    /// Arguments validation failed before the kernel was enqueued, see
+    /// `Kernel::enable_validation`.
+    InvalidArgs(kernel::arg::ArgsError),
+
+    /// The argument at index `index` was rejected by the driver when setting all the arguments
+    /// of a `TypedKernel`.
+    InvalidArg {
+        /// Index of the argument.
+        index: ffi::cl_uint,
+
+        /// The error returned by `Kernel::set_arg`.
+        error: kernel::arg::SetArgError,
+    },
+
+    /// The driver reported that some arguments were not set.
+    InvalidKernelArgs,
+
+    /// The global size, local size and offset do not have the same number of dimensions, or
+    /// this number is not between 1 and 3.
+    InvalidWorkDimension,
+
+    /// The global size is zero or exceeds the range supported by the device.
+    InvalidGlobalWorkSize,
+
+    /// The global offset exceeds the range supported by the device.
+    InvalidGlobalOffset,
+
+    /// The local size does not divide the global size, exceeds the limits of the device or the
+    /// kernel, or does not match the work-group size required by the kernel.
+    InvalidWorkGroupSize,
+
+    /// The kernel and the command queue are not associated with the same context.
+    InvalidContext,
+
+    /// The program of the kernel was not successfully built for the device of the queue.
+    InvalidProgramExecutable,
+
+    /// Failed to allocate memory for the memory objects used by the kernel.
+    AllocationFailure,
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LaunchError::WaitList(err) =>
+                write!(f, "{}", err),
+            LaunchError::InvalidArgs(ref err) =>
+                write!(f, "{}", err),
+            LaunchError::InvalidArg { index, error } =>
+                write!(f, "argument {}: {}", index, error),
+            LaunchError::InvalidKernelArgs =>
+                write!(f, "some kernel arguments were not set"),
+            LaunchError::InvalidWorkDimension =>
+                write!(f, "invalid number of work dimensions"),
+            LaunchError::InvalidGlobalWorkSize =>
+                write!(f, "invalid global work size"),
+            LaunchError::InvalidGlobalOffset =>
+                write!(f, "invalid global offset"),
+            LaunchError::InvalidWorkGroupSize =>
+                write!(f, "invalid work-group size"),
+            LaunchError::InvalidContext =>
+                write!(f, "the kernel and the command queue have different contexts"),
+            LaunchError::InvalidProgramExecutable =>
+                write!(f, "the program was not built for the device of the command queue"),
+            LaunchError::AllocationFailure =>
+                write!(f, "failed to allocate memory"),
+        }
+    }
+}
+
+/// An error returned by the memory transfer operations of `CommandQueue`, such as
+/// `CommandQueue::enqueue_write_buffer`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum TransferError {
+    /// The wait list is invalid or one of its events failed.
+    WaitList(WaitListError),
+
+    /// The accessed range exceeds the size of a buffer or of a host slice.
+    OutOfBounds,
+
+    /// A `Region3` is empty, has rows or slices overlapping each other, or does not have the
+    /// same size as the other region of the transfer.
+    InvalidRegion,
+
+    /// The size of the fill pattern is not a power of two lower than or equal to 128 bytes.
+    InvalidPattern,
+
+    /// The source and destination ranges of a copy within the same buffer overlap.
+    Overlap,
+
+    /// A buffer and the command queue are not associated with the same context.
+    InvalidContext,
+
+    /// Failed to allocate memory for a buffer.
+    AllocationFailure,
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransferError::WaitList(err) =>
+                write!(f, "{}", err),
+            TransferError::OutOfBounds =>
+                write!(f, "the accessed range exceeds the size of the buffer"),
+            TransferError::InvalidRegion =>
+                write!(f, "invalid region"),
+            TransferError::InvalidPattern =>
+                write!(f, "the size of the pattern is not a power of two up to 128 bytes"),
+            TransferError::Overlap =>
+                write!(f, "the source and destination ranges overlap"),
+            TransferError::InvalidContext =>
+                write!(f, "the buffer and the command queue have different contexts"),
+            TransferError::AllocationFailure =>
+                write!(f, "failed to allocate memory"),
+        }
+    }
+}
+
+/// An error returned by `CommandQueue::run_scalar_task`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ScalarTaskError {
+    /// The result buffer could not be created.
+    Buffer(cl_mem::CreationError),
+
+    /// The task could not be launched.
+    Launch(LaunchError),
+
+    /// The result could not be read back, e.g. because the task failed.
+    Transfer(TransferError),
+}
+
+impl fmt::Display for ScalarTaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScalarTaskError::Buffer(err) =>
+                write!(f, "{}", err),
+            ScalarTaskError::Launch(ref err) =>
+                write!(f, "{}", err),
+            ScalarTaskError::Transfer(err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+/// An error returned by `CommandQueue::enqueue_native`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum NativeError {
+    /// The wait list is invalid or one of its events failed.
+    WaitList(WaitListError),
+
+    /// The device of the command queue cannot execute native kernels (see
+    /// `device::ExecutionCapabilities::native_kernel`).
+    NotSupported,
+
+    /// The same buffer was passed several times.
+    AliasedBuffers,
+
+    /// A buffer and the command queue are not associated with the same context.
+    InvalidContext,
+
+    /// Failed to allocate memory for a buffer.
+    AllocationFailure,
+}
+
+impl fmt::Display for NativeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NativeError::WaitList(err) =>
+                write!(f, "{}", err),
+            NativeError::NotSupported =>
+                write!(f, "the device cannot execute native kernels"),
+            NativeError::AliasedBuffers =>
+                write!(f, "the same buffer was passed several times"),
+            NativeError::InvalidContext =>
+                write!(f, "a buffer and the command queue have different contexts"),
+            NativeError::AllocationFailure =>
+                write!(f, "failed to allocate memory"),
+        }
+    }
+}
+
 impl CommandQueue {
+    unsafe fn from_ffi(queue: ffi::cl_command_queue, retain: bool) -> Self {
+        if retain {
+            catch_ffi(ffi::clRetainCommandQueue(queue)).unwrap();
+        }
+
+        CommandQueue {
+            queue,
+            wait_list_sync: AtomicUsize::new(SYNC_UNKNOWN),
+        }
+    }
+
     /// Create a command queue on a device associated with a context.
     ///
     /// # Examples
@@ -126,7 +589,10 @@ impl CommandQueue {
         }
 
         // Other errors will cause panic.
-        let result = catch_ffi(error).map(|()| CommandQueue { queue });
+        let result = catch_ffi(error).map(|()| CommandQueue {
+            queue,
+            wait_list_sync: AtomicUsize::new(SYNC_UNKNOWN),
+        });
         Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
     }
 
@@ -181,54 +647,1531 @@ impl CommandQueue {
             ffi::CL_INVALID_VALUE
         )
     }
-}
 
-impl Clone for CommandQueue {
-    fn clone(&self) -> Self {
-        catch_ffi(unsafe { ffi::clRetainCommandQueue(self.queue) }).unwrap();
+    /// Enqueue a command executing `kernel` over `range`, with the arguments currently set on
+    /// `kernel`. If validation was enabled on `kernel`, the arguments are checked before
+    /// anything is submitted to the driver.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, Buffer, program, mem, Future};
+    /// use gprust::command_queue::{self, NdRange};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void twice(__global float * data) {
+    ///         data[get_global_id(0)] *= 2;
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// let program = program.build_with_options("-cl-kernel-arg-info")
+    ///                      .wait()
+    ///                      .map_err(|_| "build failed")?;
+    ///
+    /// let buffer = Buffer::create(vec![1.0f32; 16], &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    /// let mut kernel = program.create_kernel("twice").map_err(|_| "no such kernel")?;
+    /// kernel.enable_validation().map_err(|_| "no argument information")?;
+    /// kernel.set_arg(0, &buffer).map_err(|_| "invalid argument")?;
+    ///
+    /// let event = queue.enqueue_nd_range(&kernel, &NdRange::new(&[16]))
+    ///                  .map_err(|_| "failed to enqueue kernel")?;
+    /// event.wait().map_err(|_| "kernel failed")?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `LaunchError::InvalidArgs(errors)` if validation is enabled on `kernel` and some
+    /// arguments are unset or do not match the kernel parameters.
+    /// * `LaunchError::InvalidWorkDimension` if the dimensions of `range` are invalid.
+    /// * Other variants of `LaunchError` map the errors reported by the driver, see their
+    /// documentation.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_nd_range(&self, kernel: &Kernel, range: &NdRange) -> Result<Event, LaunchError> {
+        self.after(&[]).enqueue_nd_range(kernel, range)
+    }
 
-        CommandQueue {
-            queue: self.queue,
-        }
+    /// Issue all the commands previously enqueued to the device, without waiting for them to
+    /// complete.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn flush(&self) {
+        let error = unsafe { ffi::clFlush(self.queue) };
+        expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
     }
-}
 
-impl Drop for CommandQueue {
-    fn drop(&mut self) {
-        catch_ffi(unsafe { ffi::clReleaseCommandQueue(self.queue) }).unwrap();
+    /// Block until all the commands previously enqueued have completed.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn finish(&self) {
+        let error = unsafe { ffi::clFinish(self.queue) };
+        expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
     }
-}
 
-impl fmt::Debug for CommandQueue {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("CommandQueue")
-         .field("context", &self.get_info::<information::Context>())
-         .field("device", &self.get_info::<information::Device>())
-         .finish()
+    /// Enqueue a marker, whose event completes once all the commands previously enqueued have
+    /// completed. Use `CommandQueue::after` to wait for specific events instead.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_marker(&self) -> Event {
+        self.after(&[]).enqueue_marker().expect("an empty wait list is always valid")
     }
-}
 
-#[test]
-fn test_relation_to_context_and_device() {
-    let context = Context::default().unwrap();
-    let device = Device::default().unwrap();
-    let queue = CommandQueue::create(&context, &device, Properties::new()).unwrap();
+    /// Enqueue a barrier: the commands enqueued afterwards only start once all the commands
+    /// previously enqueued have completed. Use `CommandQueue::after` to wait for specific
+    /// events instead.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_barrier(&self) -> Event {
+        self.after(&[]).enqueue_barrier().expect("an empty wait list is always valid")
+    }
 
-    assert_eq!(context, queue.get_info::<information::Context>());
-    assert_eq!(device, queue.get_info::<information::Device>());
-}
+    /// Enqueue a command writing `data` to `buffer`, starting at element `offset`. `data` is
+    /// copied beforehand, so that the command does not borrow it.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, Buffer, mem, command_queue};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    /// let buffer = Buffer::create(vec![0i32; 4], &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    ///
+    /// queue.enqueue_write_buffer(&buffer, 1, &[1, 2]).map_err(|_| "failed to write")?;
+    /// let mut data = [0; 4];
+    /// queue.enqueue_read_buffer(&buffer, 0, &mut data).map_err(|_| "failed to read")?;
+    /// assert_eq!(data, [0, 1, 2, 0]);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `TransferError::OutOfBounds` if the written range exceeds the size of `buffer`.
+    /// * `TransferError::InvalidContext` if `buffer` is associated with another context.
+    /// * `TransferError::AllocationFailure` if memory could not be allocated for `buffer`.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_write_buffer<T>(&self, buffer: &Buffer<T>, offset: usize, data: &[T])
+        -> Result<Event, TransferError>
+        where T: Copy + Send + 'static
+    {
+        self.after(&[]).enqueue_write_buffer(buffer, offset, data)
+    }
 
-#[test]
-fn test_relation_to_properties() {
-    let properties = PropertiesBuilder::new().profiling().finish();
-    let queue = CommandQueue::create(
-        &Context::default().unwrap(),
-        &Device::default().unwrap(),
-        properties.clone()
-    );
-    
-    // In case `CL_QUEUE_PROFILING_ENABLE` is not supported...
-    if let Ok(queue) = queue {
-        assert_eq!(properties, queue.get_info::<information::Properties>());
+    /// Read `buffer` into `data`, starting at element `offset`. This blocks until the data has
+    /// been read, the returned event being complete.
+    ///
+    /// # Errors
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_read_buffer<T: Copy>(&self, buffer: &Buffer<T>, offset: usize, data: &mut [T])
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).enqueue_read_buffer(buffer, offset, data)
+    }
+
+    /// Enqueue a command copying `len` elements of `src` starting at element `src_offset` to
+    /// `dst` starting at element `dst_offset`.
+    ///
+    /// # Errors
+    /// * `TransferError::Overlap` if `src` and `dst` are the same buffer and the source and
+    /// destination ranges overlap.
+    /// * Same as `CommandQueue::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_copy_buffer<T>(&self, src: &Buffer<T>, src_offset: usize, dst: &Buffer<T>,
+                                  dst_offset: usize, len: usize)
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).enqueue_copy_buffer(src, src_offset, dst, dst_offset, len)
     }
+
+    /// Enqueue a command executing `kernel` as a single work-item, with the arguments currently
+    /// set on `kernel`. This is equivalent to a one-dimensional range of size 1 with a local
+    /// size of 1, and is the preferred way of launching kernels on some devices such as FPGAs.
+    ///
+    /// # Errors
+    /// Same as `CommandQueue::enqueue_nd_range`.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_nd_range`.
+    pub fn enqueue_task(&self, kernel: &Kernel) -> Result<Event, LaunchError> {
+        self.after(&[]).enqueue_task(kernel)
+    }
+
+    /// Run `kernel` as a single work-item and return the scalar it writes to its parameter at
+    /// index `result_index`, which must be a `__global` pointer to `T`. A one-element buffer
+    /// is created and set as this argument, the other arguments must have been set
+    /// beforehand. This blocks until the result has been read.
+    ///
+    /// The buffer is released on return, so the argument at index `result_index` must be set
+    /// again before `kernel` is enqueued anew: it is left unset, as reported by
+    /// `Kernel::validate_args`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, program, command_queue, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void sum(uint n, __global ulong * result) {
+    ///         ulong sum = 0;
+    ///         for (uint i = 1; i <= n; ++i) sum += i;
+    ///         *result = sum;
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// let program = program.build().wait().map_err(|_| "build failed")?;
+    /// let mut kernel = program.create_kernel("sum").map_err(|_| "no such kernel")?;
+    ///
+    /// kernel.set_arg(0, &100u32).map_err(|_| "invalid argument")?;
+    /// let sum: u64 = queue.run_scalar_task(&mut kernel, 1).map_err(|_| "task failed")?;
+    /// assert_eq!(sum, 5050);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `ScalarTaskError::Buffer(err)` if the result buffer could not be created.
+    /// * `ScalarTaskError::Launch(err)` if the result argument could not be set or the task
+    /// could not be launched, see `CommandQueue::enqueue_task`.
+    /// * `ScalarTaskError::Transfer(err)` if the result could not be read, in particular
+    /// `TransferError::WaitList(WaitListError::FailedDependency { .. })` or
+    /// `TransferError::WaitList(WaitListError::FailedIndirectDependency)` if the task failed.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_task`.
+    pub fn run_scalar_task<T>(&self, kernel: &mut Kernel, result_index: ffi::cl_uint)
+        -> Result<T, ScalarTaskError>
+        where T: Scalar + Default
+    {
+        let context = self.get_info::<information::Context>();
+        let result = Buffer::create(Some(T::default()), &context, cl_mem::Flags::new())
+            .map_err(ScalarTaskError::Buffer)?;
+        kernel.set_arg(result_index, &result).map_err(|error| {
+            ScalarTaskError::Launch(LaunchError::InvalidArg { index: result_index, error })
+        })?;
+
+        let event = self.enqueue_task(kernel);
+        kernel.clear_arg(result_index);
+
+        let event = event.map_err(ScalarTaskError::Launch)?;
+        let mut value = [T::default()];
+        self.after(&[&event])
+            .enqueue_read_buffer(&result, 0, &mut value)
+            .map_err(ScalarTaskError::Transfer)?;
+        Ok(value[0])
+    }
+
+    /// Enqueue a command filling `len` elements of `buffer` starting at element `offset` with
+    /// `pattern`.
+    ///
+    /// # Errors
+    /// * `TransferError::InvalidPattern` if the size of `T` is not a power of two lower than or
+    /// equal to 128 bytes.
+    /// * Same as `CommandQueue::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_fill_buffer<T: Copy>(&self, buffer: &Buffer<T>, pattern: T, offset: usize,
+                                        len: usize)
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).enqueue_fill_buffer(buffer, pattern, offset, len)
+    }
+
+    /// Read the box `buffer_region` of `buffer` into the box `host_region` of `host`, both
+    /// regions having the same size. This blocks until the data has been read, the returned
+    /// event being complete.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, Buffer, mem};
+    /// use gprust::command_queue::{self, Region3};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    ///
+    /// // A 4x4 image, from which the 2x2 center is read.
+    /// let image = Buffer::create(0..16, &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    /// let center = Region3::new([2, 2, 1]).set_origin([1, 1, 0]).set_row_pitch(4);
+    ///
+    /// let mut data = [0; 4];
+    /// queue.enqueue_read_buffer_rect(&image, &center, &mut data, &Region3::new([2, 2, 1]))
+    ///      .map_err(|_| "failed to read")?;
+    /// assert_eq!(data, [5, 6, 9, 10]);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `TransferError::InvalidRegion` if a region is invalid or the sizes differ.
+    /// * `TransferError::OutOfBounds` if a region exceeds `buffer` or `host`.
+    /// * Same as `CommandQueue::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_read_buffer_rect<T: Copy>(&self, buffer: &Buffer<T>, buffer_region: &Region3,
+                                             host: &mut [T], host_region: &Region3)
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).enqueue_read_buffer_rect(buffer, buffer_region, host, host_region)
+    }
+
+    /// Write the box `host_region` of `host` into the box `buffer_region` of `buffer`, both
+    /// regions having the same size, e.g. for uploading a part of pitched host data without
+    /// repacking it. This blocks until `host` has been read, the returned event being complete.
+    ///
+    /// # Errors
+    /// Same as `CommandQueue::enqueue_read_buffer_rect`.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_write_buffer_rect<T: Copy>(&self, buffer: &Buffer<T>, buffer_region: &Region3,
+                                              host: &[T], host_region: &Region3)
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).enqueue_write_buffer_rect(buffer, buffer_region, host, host_region)
+    }
+
+    /// Enqueue a command copying the box `src_region` of `src` to the box `dst_region` of
+    /// `dst`, both regions having the same size.
+    ///
+    /// # Errors
+    /// * `TransferError::Overlap` if `src` and `dst` are the same buffer and the regions
+    /// overlap, which is detected before anything is submitted to the driver.
+    /// * Same as `CommandQueue::enqueue_read_buffer_rect` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_copy_buffer_rect<T>(&self, src: &Buffer<T>, src_region: &Region3,
+                                       dst: &Buffer<T>, dst_region: &Region3)
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).enqueue_copy_buffer_rect(src, src_region, dst, dst_region)
+    }
+
+    /// Enqueue a command migrating `buffers` to the device of the command queue, or to the host
+    /// if the `host` flag is set, e.g. for staging the inputs of a kernel on the device which
+    /// will consume them when a context holds several devices. If the `content_undefined` flag
+    /// is set, the contents of the buffers are not migrated, which is cheaper when they are
+    /// about to be overwritten. The buffers may have different element types.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, Buffer, mem, Future};
+    /// use gprust::command_queue::{self, MigrationFlags};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    /// let data = Buffer::create(vec![0.0f32; 1024], &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    /// let indices = Buffer::create(0..1024i32, &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    ///
+    /// queue.migrate(&[&data, &indices], MigrationFlags::new())
+    ///      .map_err(|_| "failed to migrate")?
+    ///      .wait()
+    ///      .map_err(|_| "migration failed")?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `TransferError::InvalidContext` if a buffer is associated with another context.
+    /// * `TransferError::AllocationFailure` if memory could not be allocated for a buffer.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn migrate(&self, buffers: &[&dyn MemObject], flags: MigrationFlags)
+        -> Result<Event, TransferError>
+    {
+        self.after(&[]).migrate(buffers, flags)
+    }
+
+    /// Enqueue a command running `function` on the host, as a native kernel of the device.
+    /// `function` is given the contents of `buffers`, in order, as mutable slices: the memory
+    /// objects are mapped to host memory by the runtime for the duration of the command. This
+    /// lets host steps take part in device pipelines on CPU runtimes such as pocl.
+    ///
+    /// A panic in `function` is caught and ignored, the command being considered complete.
+    ///
+    /// If the command never runs, e.g. because an event of the wait list failed, `function`
+    /// and everything it captures are leaked.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, Buffer, mem, command_queue, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    /// let buffer = Buffer::create(vec![1i32, 2, 3], &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    ///
+    /// let event = queue.enqueue_native(|data: &mut [&mut [i32]]| {
+    ///     for x in data[0].iter_mut() {
+    ///         *x *= 2;
+    ///     }
+    /// }, &[&buffer]);
+    ///
+    /// if let Ok(event) = event {
+    ///     event.wait().map_err(|_| "native kernel failed")?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `NativeError::NotSupported` if the device cannot execute native kernels.
+    /// * `NativeError::AliasedBuffers` if the same buffer appears several times in `buffers`.
+    /// * `NativeError::InvalidContext` if a buffer is associated with another context.
+    /// * `NativeError::AllocationFailure` if memory could not be allocated for a buffer.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_native<T, F>(&self, function: F, buffers: &[&Buffer<T>])
+        -> Result<Event, NativeError>
+        where F: FnOnce(&mut [&mut [T]]) + Send + 'static
+    {
+        self.after(&[]).enqueue_native(function, buffers)
+    }
+
+    // Return whether the device of the queue supports OpenCL 1.2, which introduced
+    // `clEnqueueMarkerWithWaitList` and `clEnqueueBarrierWithWaitList`. The device is only
+    // queried the first time.
+    fn supports_wait_list_sync(&self) -> bool {
+        match self.wait_list_sync.load(Ordering::Relaxed) {
+            SYNC_SUPPORTED => return true,
+            SYNC_UNSUPPORTED => return false,
+            _ => (),
+        }
+
+        let device = self.get_info::<information::Device>();
+        let version = device.get_info::<device::information::Version>();
+        let supported = match device::parse_version(&version, "OpenCL ") {
+            Some(version) => version >= (1, 2),
+            None => true,
+        };
+
+        let state = if supported { SYNC_SUPPORTED } else { SYNC_UNSUPPORTED };
+        self.wait_list_sync.store(state, Ordering::Relaxed);
+        supported
+    }
+
+    /// Return a view of the command queue whose enqueue operations only start once all the
+    /// commands associated with `events` have completed. All the enqueue operations of
+    /// `CommandQueue` are available on the returned `WaitList`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{CommandQueue, program, Future};
+    /// use gprust::command_queue::{self, NdRange};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let queue = CommandQueue::default().ok_or("no default command queue")?;
+    /// let context = queue.get_info::<command_queue::information::Context>();
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void first() { } __kernel void second() { }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// let program = program.build().wait().map_err(|_| "build failed")?;
+    /// let first = program.create_kernel("first").map_err(|_| "no such kernel")?;
+    /// let second = program.create_kernel("second").map_err(|_| "no such kernel")?;
+    ///
+    /// let range = NdRange::new(&[16]);
+    /// let a = queue.enqueue_nd_range(&first, &range).map_err(|_| "failed to enqueue kernel")?;
+    /// let b = queue.enqueue_nd_range(&first, &range).map_err(|_| "failed to enqueue kernel")?;
+    /// let event = queue.after(&[&a, &b])
+    ///                  .enqueue_nd_range(&second, &range)
+    ///                  .map_err(|_| "failed to enqueue kernel")?;
+    /// event.wait().map_err(|_| "kernel failed")?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    pub fn after<'a>(&'a self, events: &'a [&'a Event]) -> WaitList<'a> {
+        WaitList {
+            queue: self,
+            events,
+        }
+    }
+}
+
+/// A command queue along with a list of events which must complete before the next enqueued
+/// command starts, as returned by `CommandQueue::after`.
+///
+/// Every event of the wait list must be associated with the context of the command queue,
+/// which is checked before anything is submitted to the driver. If one of the events was
+/// already abnormally terminated when a command is enqueued, the error is reported by naming
+/// the failed event. If an event fails after the command was submitted, the command is
+/// terminated as well and the failure is reported by the event returned by the enqueue
+/// operation, as an `ExecutionError`.
+#[derive(Clone, Copy, Debug)]
+pub struct WaitList<'a> {
+    queue: &'a CommandQueue,
+    events: &'a [&'a Event],
+}
+
+impl<'a> From<&'a CommandQueue> for WaitList<'a> {
+    fn from(queue: &'a CommandQueue) -> Self {
+        queue.after(&[])
+    }
+}
+
+impl<'a> WaitList<'a> {
+    /// Return the command queue.
+    pub fn queue(&self) -> &'a CommandQueue {
+        self.queue
+    }
+
+    /// Return the events of the wait list.
+    pub fn events(&self) -> &'a [&'a Event] {
+        self.events
+    }
+
+    // Check that all the events belong to the context of the queue and return their underlying
+    // handles.
+    fn underlying(&self) -> Result<Vec<ffi::cl_event>, WaitListError> {
+        if self.events.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let context = self.queue.get_info::<information::Context>();
+        self.events.iter().enumerate().map(|(index, event)| {
+            if event.get_info::<event::information::Context>() != context {
+                return Err(WaitListError::ContextMismatch { index });
+            }
+
+            // Drivers only report failed dependencies for blocking calls, so check them
+            // before submitting.
+            match event.status() {
+                event::Status::Error(status) =>
+                    Err(WaitListError::FailedDependency { index, status }),
+                _ => Ok(unsafe { event.underlying() }),
+            }
+        }).collect()
+    }
+
+    // Find the failed event after the driver returned
+    // `CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST`.
+    fn failed_dependency(&self) -> WaitListError {
+        self.events.iter().enumerate().filter_map(|(index, event)| {
+            match event.status() {
+                event::Status::Error(status) => Some(WaitListError::FailedDependency { index, status }),
+                _ => None,
+            }
+        }).next().unwrap_or(WaitListError::FailedIndirectDependency)
+    }
+
+    /// Same as `CommandQueue::enqueue_nd_range`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// * `LaunchError::WaitList(err)` if the wait list is invalid or one of its events failed.
+    /// * Same as `CommandQueue::enqueue_nd_range` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_nd_range`.
+    pub fn enqueue_nd_range(&self, kernel: &Kernel, range: &NdRange) -> Result<Event, LaunchError> {
+        if kernel.validation_enabled() {
+            kernel.validate_args().map_err(LaunchError::InvalidArgs)?;
+        }
+
+        self.enqueue_nd_range_unchecked(kernel, range)
+    }
+
+    // Same as `enqueue_nd_range` without validating the arguments, for callers which already
+    // did.
+    pub(super) fn enqueue_nd_range_unchecked(&self, kernel: &Kernel, range: &NdRange)
+        -> Result<Event, LaunchError>
+    {
+        if !range.is_valid() {
+            return Err(LaunchError::InvalidWorkDimension);
+        }
+
+        let wait_list = self.underlying().map_err(LaunchError::WaitList)?;
+        let as_ptr = |sizes: Option<&[usize]>| sizes.map_or(ptr::null(), |sizes| sizes.as_ptr());
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueNDRangeKernel(
+                self.queue.queue,
+                kernel.underlying(),
+                range.dimensions() as ffi::cl_uint,
+                as_ptr(range.offset()),
+                range.global().as_ptr(),
+                as_ptr(range.local()),
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.launch_result(error, event)
+    }
+
+    /// Same as `CommandQueue::enqueue_task`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// * `LaunchError::WaitList(err)` if the wait list is invalid or one of its events failed.
+    /// * Same as `CommandQueue::enqueue_task` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_task`.
+    pub fn enqueue_task(&self, kernel: &Kernel) -> Result<Event, LaunchError> {
+        if kernel.validation_enabled() {
+            kernel.validate_args().map_err(LaunchError::InvalidArgs)?;
+        }
+
+        let wait_list = self.underlying().map_err(LaunchError::WaitList)?;
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueTask(
+                self.queue.queue,
+                kernel.underlying(),
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.launch_result(error, event)
+    }
+
+    // Map the error returned by a kernel launch.
+    fn launch_result(&self, error: ffi::cl_int, event: ffi::cl_event)
+        -> Result<Event, LaunchError>
+    {
+        match error {
+            ffi::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST =>
+                return Err(LaunchError::WaitList(self.failed_dependency())),
+            ffi::CL_INVALID_KERNEL_ARGS => return Err(LaunchError::InvalidKernelArgs),
+            ffi::CL_INVALID_WORK_DIMENSION => return Err(LaunchError::InvalidWorkDimension),
+            ffi::CL_INVALID_GLOBAL_WORK_SIZE => return Err(LaunchError::InvalidGlobalWorkSize),
+            ffi::CL_INVALID_GLOBAL_OFFSET => return Err(LaunchError::InvalidGlobalOffset),
+            ffi::CL_INVALID_WORK_GROUP_SIZE | ffi::CL_INVALID_WORK_ITEM_SIZE =>
+                return Err(LaunchError::InvalidWorkGroupSize),
+            ffi::CL_INVALID_CONTEXT => return Err(LaunchError::InvalidContext),
+            ffi::CL_INVALID_PROGRAM_EXECUTABLE => return Err(LaunchError::InvalidProgramExecutable),
+            ffi::CL_MEM_OBJECT_ALLOCATION_FAILURE => return Err(LaunchError::AllocationFailure),
+            _ => (),
+        }
+
+        // Other errors will cause panic.
+        let result = catch_ffi(error).map(|()| unsafe { Event::from_ffi(event, false) });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+
+    /// Same as `CommandQueue::enqueue_write_buffer`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// * `TransferError::WaitList(err)` if the wait list is invalid or one of its events failed.
+    /// * Same as `CommandQueue::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_write_buffer<T>(&self, buffer: &Buffer<T>, offset: usize, data: &[T])
+        -> Result<Event, TransferError>
+        where T: Copy + Send + 'static
+    {
+        check_bounds(buffer, offset, data.len())?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        // The copy is released once the command has completed.
+        let data: Box<[T]> = data.to_vec().into_boxed_slice();
+        let size = mem::size_of_val(&*data);
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueWriteBuffer(
+                self.queue.queue,
+                buffer.underlying(),
+                ffi::CL_FALSE,
+                offset * mem::size_of::<T>(),
+                size,
+                data.as_ptr() as _,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        let event = self.transfer_result(error, event)?;
+        event.on_complete(move || drop(data));
+        Ok(event)
+    }
+
+    /// Same as `CommandQueue::enqueue_read_buffer`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// Same as `WaitList::enqueue_write_buffer`.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_read_buffer<T: Copy>(&self, buffer: &Buffer<T>, offset: usize, data: &mut [T])
+        -> Result<Event, TransferError>
+    {
+        check_bounds(buffer, offset, data.len())?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueReadBuffer(
+                self.queue.queue,
+                buffer.underlying(),
+                ffi::CL_TRUE,
+                offset * mem::size_of::<T>(),
+                mem::size_of_val(data),
+                data.as_mut_ptr() as _,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    /// Same as `CommandQueue::enqueue_copy_buffer`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// * `TransferError::Overlap` if `src` and `dst` are the same buffer and the source and
+    /// destination ranges overlap.
+    /// * Same as `WaitList::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_copy_buffer<T>(&self, src: &Buffer<T>, src_offset: usize, dst: &Buffer<T>,
+                                  dst_offset: usize, len: usize)
+        -> Result<Event, TransferError>
+    {
+        check_bounds(src, src_offset, len)?;
+        check_bounds(dst, dst_offset, len)?;
+        if src == dst && src_offset < dst_offset + len && dst_offset < src_offset + len {
+            return Err(TransferError::Overlap);
+        }
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        let size = mem::size_of::<T>();
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueCopyBuffer(
+                self.queue.queue,
+                src.underlying(),
+                dst.underlying(),
+                src_offset * size,
+                dst_offset * size,
+                len * size,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    /// Same as `CommandQueue::enqueue_native`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// * `NativeError::WaitList(err)` if the wait list is invalid or one of its events failed.
+    /// * Same as `CommandQueue::enqueue_native` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_native`.
+    pub fn enqueue_native<T, F>(&self, function: F, buffers: &[&Buffer<T>])
+        -> Result<Event, NativeError>
+        where F: FnOnce(&mut [&mut [T]]) + Send + 'static
+    {
+        let device = self.queue.get_info::<information::Device>();
+        if !device.get_info::<device::information::ExecutionCapabilities>().native_kernel() {
+            return Err(NativeError::NotSupported);
+        }
+
+        for (index, buffer) in buffers.iter().enumerate() {
+            if buffers[..index].contains(buffer) {
+                return Err(NativeError::AliasedBuffers);
+            }
+        }
+
+        let wait_list = self.underlying().map_err(NativeError::WaitList)?;
+
+        // The arguments block holds the boxed closure, the number of buffers and a pair
+        // (memory object, length) per buffer. The runtime copies the block and replaces the
+        // memory objects with host pointers.
+        let function = Box::into_raw(Box::new(function));
+        let mut args = vec![function as usize, buffers.len()];
+        let mut mems = Vec::with_capacity(buffers.len());
+        for buffer in buffers {
+            let handle = unsafe { buffer.underlying() };
+            mems.push(handle);
+            args.push(handle as usize);
+            args.push(buffer.len());
+        }
+        let locations: Vec<*const c_void> = (0..buffers.len()).map(|index| {
+            &args[2 + 2 * index] as *const usize as *const c_void
+        }).collect();
+
+        // The driver expects null pointers when there is no buffer.
+        let (mems_ptr, locations_ptr) = if buffers.is_empty() {
+            (ptr::null(), ptr::null())
+        } else {
+            (mems.as_ptr(), locations.as_ptr())
+        };
+
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueNativeKernel(
+                self.queue.queue,
+                Some(run_native::<T, F>),
+                args.as_mut_ptr() as _,
+                args.len() * mem::size_of::<usize>(),
+                mems.len() as ffi::cl_uint,
+                mems_ptr,
+                locations_ptr as _,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        if error != ffi::CL_SUCCESS {
+            drop(unsafe { Box::from_raw(function) });
+        }
+
+        match error {
+            ffi::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST =>
+                return Err(NativeError::WaitList(self.failed_dependency())),
+            ffi::CL_INVALID_OPERATION => return Err(NativeError::NotSupported),
+            ffi::CL_INVALID_CONTEXT => return Err(NativeError::InvalidContext),
+            ffi::CL_MEM_OBJECT_ALLOCATION_FAILURE => return Err(NativeError::AllocationFailure),
+            _ => (),
+        }
+
+        // Other errors will cause panic.
+        let result = catch_ffi(error).map(|()| unsafe { Event::from_ffi(event, false) });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+
+    /// Same as `CommandQueue::enqueue_fill_buffer`, waiting for the events of the wait list.
+    ///
+    /// # Errors
+    /// * `TransferError::InvalidPattern` if the size of `T` is not a power of two lower than or
+    /// equal to 128 bytes.
+    /// * Same as `WaitList::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_fill_buffer<T: Copy>(&self, buffer: &Buffer<T>, pattern: T, offset: usize,
+                                        len: usize)
+        -> Result<Event, TransferError>
+    {
+        let size = mem::size_of::<T>();
+        if !size.is_power_of_two() || size > 128 {
+            return Err(TransferError::InvalidPattern);
+        }
+        check_bounds(buffer, offset, len)?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        // The pattern is copied by the driver.
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueFillBuffer(
+                self.queue.queue,
+                buffer.underlying(),
+                &pattern as *const T as _,
+                size,
+                offset * size,
+                len * size,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    /// Same as `CommandQueue::enqueue_read_buffer_rect`, waiting for the events of the wait
+    /// list.
+    ///
+    /// # Errors
+    /// * `TransferError::WaitList(err)` if the wait list is invalid or one of its events failed.
+    /// * Same as `CommandQueue::enqueue_read_buffer_rect` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_read_buffer_rect<T: Copy>(&self, buffer: &Buffer<T>, buffer_region: &Region3,
+                                             host: &mut [T], host_region: &Region3)
+        -> Result<Event, TransferError>
+    {
+        check_regions(buffer_region, buffer.len(), host_region, host.len())?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        let size = mem::size_of::<T>();
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueReadBufferRect(
+                self.queue.queue,
+                buffer.underlying(),
+                ffi::CL_TRUE,
+                buffer_region.origin_in_bytes(size).as_ptr(),
+                host_region.origin_in_bytes(size).as_ptr(),
+                buffer_region.size_in_bytes(size).as_ptr(),
+                buffer_region.row_pitch() * size,
+                buffer_region.slice_pitch() * size,
+                host_region.row_pitch() * size,
+                host_region.slice_pitch() * size,
+                host.as_mut_ptr() as _,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    /// Same as `CommandQueue::enqueue_write_buffer_rect`, waiting for the events of the wait
+    /// list.
+    ///
+    /// # Errors
+    /// Same as `WaitList::enqueue_read_buffer_rect`.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_write_buffer_rect<T: Copy>(&self, buffer: &Buffer<T>, buffer_region: &Region3,
+                                              host: &[T], host_region: &Region3)
+        -> Result<Event, TransferError>
+    {
+        check_regions(buffer_region, buffer.len(), host_region, host.len())?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        let size = mem::size_of::<T>();
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueWriteBufferRect(
+                self.queue.queue,
+                buffer.underlying(),
+                ffi::CL_TRUE,
+                buffer_region.origin_in_bytes(size).as_ptr(),
+                host_region.origin_in_bytes(size).as_ptr(),
+                buffer_region.size_in_bytes(size).as_ptr(),
+                buffer_region.row_pitch() * size,
+                buffer_region.slice_pitch() * size,
+                host_region.row_pitch() * size,
+                host_region.slice_pitch() * size,
+                host.as_ptr() as _,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    /// Same as `CommandQueue::enqueue_copy_buffer_rect`, waiting for the events of the wait
+    /// list.
+    ///
+    /// # Errors
+    /// * `TransferError::Overlap` if `src` and `dst` are the same buffer and the regions
+    /// overlap.
+    /// * Same as `WaitList::enqueue_read_buffer_rect` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_copy_buffer_rect<T>(&self, src: &Buffer<T>, src_region: &Region3,
+                                       dst: &Buffer<T>, dst_region: &Region3)
+        -> Result<Event, TransferError>
+    {
+        check_regions(src_region, src.len(), dst_region, dst.len())?;
+        if src == dst && overlap(src_region, dst_region)? {
+            return Err(TransferError::Overlap);
+        }
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+
+        let size = mem::size_of::<T>();
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueCopyBufferRect(
+                self.queue.queue,
+                src.underlying(),
+                dst.underlying(),
+                src_region.origin_in_bytes(size).as_ptr(),
+                dst_region.origin_in_bytes(size).as_ptr(),
+                src_region.size_in_bytes(size).as_ptr(),
+                src_region.row_pitch() * size,
+                src_region.slice_pitch() * size,
+                dst_region.row_pitch() * size,
+                dst_region.slice_pitch() * size,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    /// Same as `CommandQueue::migrate`, waiting for the events of the wait list. If `buffers`
+    /// is empty, a marker is enqueued instead.
+    ///
+    /// # Errors
+    /// * `TransferError::WaitList(err)` if the wait list is invalid or one of its events failed.
+    /// * Same as `CommandQueue::migrate` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::migrate`.
+    pub fn migrate(&self, buffers: &[&dyn MemObject], flags: MigrationFlags)
+        -> Result<Event, TransferError>
+    {
+        if buffers.is_empty() {
+            return self.enqueue_marker().map_err(TransferError::WaitList);
+        }
+
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+        let mems: Vec<_> = buffers.iter()
+                                  .map(|buffer| unsafe { buffer.underlying_mem() })
+                                  .collect();
+        let mut event = ptr::null_mut();
+        let error = unsafe {
+            ffi::clEnqueueMigrateMemObjects(
+                self.queue.queue,
+                mems.len() as ffi::cl_uint,
+                mems.as_ptr(),
+                flags.bitfield,
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event
+            )
+        };
+
+        self.transfer_result(error, event)
+    }
+
+    // Enqueue a blocking command mapping the first `len` elements of `buffer` for writing, and
+    // return the mapped pointer, valid until `unmap` is called. The previous contents are not
+    // copied to the mapped memory, which should be entirely overwritten.
+    pub(super) fn map_write<T>(&self, buffer: &Buffer<T>, len: usize)
+        -> Result<*mut T, TransferError>
+    {
+        check_bounds(buffer, 0, len)?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+        let mut event = ptr::null_mut();
+        let mut error = 0;
+        let mapped = unsafe {
+            ffi::clEnqueueMapBuffer(
+                self.queue.queue,
+                buffer.underlying(),
+                ffi::CL_TRUE,
+                ffi::CL_MAP_WRITE_INVALIDATE_REGION,
+                0,
+                len * mem::size_of::<T>(),
+                wait_list.len() as ffi::cl_uint,
+                as_wait_list(&wait_list),
+                &mut event,
+                &mut error
+            )
+        };
+
+        if error == ffi::CL_MAP_FAILURE {
+            return Err(TransferError::AllocationFailure);
+        }
+        self.transfer_result(error, event).map(|_| mapped as *mut T)
+    }
+
+    // Enqueue a command writing `len` elements from `data` to the start of `buffer`, without
+    // blocking nor copying `data`, which must stay valid until the command has completed.
+    pub(super) unsafe fn write_from<T>(&self, buffer: &Buffer<T>, data: *const T, len: usize)
+        -> Result<Event, TransferError>
+    {
+        check_bounds(buffer, 0, len)?;
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+        let mut event = ptr::null_mut();
+        let error = ffi::clEnqueueWriteBuffer(
+            self.queue.queue,
+            buffer.underlying(),
+            ffi::CL_FALSE,
+            0,
+            len * mem::size_of::<T>(),
+            data as _,
+            wait_list.len() as ffi::cl_uint,
+            as_wait_list(&wait_list),
+            &mut event
+        );
+
+        self.transfer_result(error, event)
+    }
+
+    // Enqueue a command unmapping a pointer returned by `map_write`.
+    pub(super) unsafe fn unmap<T>(&self, buffer: &Buffer<T>, mapped: *mut T)
+        -> Result<Event, TransferError>
+    {
+        let wait_list = self.underlying().map_err(TransferError::WaitList)?;
+        let mut event = ptr::null_mut();
+        let error = ffi::clEnqueueUnmapMemObject(
+            self.queue.queue,
+            buffer.underlying(),
+            mapped as _,
+            wait_list.len() as ffi::cl_uint,
+            as_wait_list(&wait_list),
+            &mut event
+        );
+
+        self.transfer_result(error, event)
+    }
+
+    // Map the error returned by a memory transfer operation.
+    fn transfer_result(&self, error: ffi::cl_int, event: ffi::cl_event)
+        -> Result<Event, TransferError>
+    {
+        match error {
+            ffi::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST =>
+                return Err(TransferError::WaitList(self.failed_dependency())),
+            ffi::CL_INVALID_CONTEXT => return Err(TransferError::InvalidContext),
+            ffi::CL_MEM_COPY_OVERLAP => return Err(TransferError::Overlap),
+            ffi::CL_MEM_OBJECT_ALLOCATION_FAILURE => return Err(TransferError::AllocationFailure),
+            _ => (),
+        }
+
+        // Other errors will cause panic.
+        let result = catch_ffi(error).map(|()| unsafe { Event::from_ffi(event, false) });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+
+    /// Enqueue a marker, whose event completes once all the events of the wait list have
+    /// completed, or all the commands previously enqueued if the wait list is empty.
+    ///
+    /// On devices predating OpenCL 1.2, a non-empty wait list is emulated through
+    /// `clEnqueueWaitForEvents`, so that the marker also acts as a barrier.
+    ///
+    /// # Errors
+    /// `WaitListError::ContextMismatch { .. }` if an event is not associated with the context
+    /// of the command queue, or `WaitListError::FailedDependency { .. }` or
+    /// `WaitListError::FailedIndirectDependency` if a dependency failed.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_marker(&self) -> Result<Event, WaitListError> {
+        self.enqueue_sync(false)
+    }
+
+    /// Enqueue a barrier: the commands enqueued afterwards only start once all the events of
+    /// the wait list have completed, or all the commands previously enqueued if the wait list
+    /// is empty. The returned event completes along with the barrier.
+    ///
+    /// # Errors
+    /// Same as `WaitList::enqueue_marker`.
+    ///
+    /// # Panics
+    /// Panic if the host or the device fails to allocate resources.
+    pub fn enqueue_barrier(&self) -> Result<Event, WaitListError> {
+        self.enqueue_sync(true)
+    }
+
+    fn enqueue_sync(&self, barrier: bool) -> Result<Event, WaitListError> {
+        let wait_list = self.underlying()?;
+        let queue = self.queue.queue;
+        let mut event = ptr::null_mut();
+
+        let error = unsafe {
+            if self.queue.supports_wait_list_sync() {
+                let enqueue = if barrier {
+                    ffi::clEnqueueBarrierWithWaitList
+                } else {
+                    ffi::clEnqueueMarkerWithWaitList
+                };
+                let count = wait_list.len() as ffi::cl_uint;
+                enqueue(queue, count, as_wait_list(&wait_list), &mut event)
+            } else {
+                // OpenCL 1.1: wait for the events, then enqueue a marker to get an event.
+                let mut error = ffi::CL_SUCCESS;
+                if !wait_list.is_empty() {
+                    let count = wait_list.len() as ffi::cl_uint;
+                    error = ffi::clEnqueueWaitForEvents(queue, count, wait_list.as_ptr());
+                }
+                if error == ffi::CL_SUCCESS && barrier {
+                    error = ffi::clEnqueueBarrier(queue);
+                }
+                if error == ffi::CL_SUCCESS {
+                    error = ffi::clEnqueueMarker(queue, &mut event);
+                }
+                error
+            }
+        };
+
+        if error == ffi::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST {
+            return Err(self.failed_dependency());
+        }
+
+        // Other errors will cause panic.
+        let result = catch_ffi(error).map(|()| unsafe { Event::from_ffi(event, false) });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+}
+
+// Entry point of the native kernels enqueued by `WaitList::enqueue_native`, `args` being the
+// copy of the arguments block made by the runtime.
+unsafe extern "C" fn run_native<T, F>(args: *mut c_void)
+    where F: FnOnce(&mut [&mut [T]])
+{
+    let args = args as *const usize;
+    let function = Box::from_raw(*args as *mut F);
+    let count = *args.add(1);
+    let mut buffers: Vec<&mut [T]> = (0..count).map(|index| {
+        let data = *args.add(2 + 2 * index) as *mut T;
+        slice::from_raw_parts_mut(data, *args.add(3 + 2 * index))
+    }).collect();
+
+    // Unwinding through the runtime is not allowed.
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(move || function(&mut buffers)));
+}
+
+// The driver expects a null pointer for an empty wait list.
+fn as_wait_list(events: &[ffi::cl_event]) -> *const ffi::cl_event {
+    if events.is_empty() { ptr::null() } else { events.as_ptr() }
+}
+
+// Check that both regions are valid, have the same size and fit in memories of `a_len` and
+// `b_len` elements.
+fn check_regions(a: &Region3, a_len: usize, b: &Region3, b_len: usize)
+    -> Result<(), TransferError>
+{
+    if !a.is_valid() || !b.is_valid() || a.size() != b.size() {
+        return Err(TransferError::InvalidRegion);
+    }
+
+    if a.end()? > a_len || b.end()? > b_len {
+        return Err(TransferError::OutOfBounds);
+    }
+
+    Ok(())
+}
+
+// Check that `len` elements starting at element `offset` fit in `buffer`.
+pub(super) fn check_bounds<T>(buffer: &Buffer<T>, offset: usize, len: usize)
+    -> Result<(), TransferError>
+{
+    match offset.checked_add(len) {
+        Some(end) if end <= buffer.len() => Ok(()),
+        _ => Err(TransferError::OutOfBounds),
+    }
+}
+
+map_ffi_impl!(CommandQueue, ffi::cl_command_queue);
+
+impl Clone for CommandQueue {
+    fn clone(&self) -> Self {
+        catch_ffi(unsafe { ffi::clRetainCommandQueue(self.queue) }).unwrap();
+
+        CommandQueue {
+            queue: self.queue,
+            wait_list_sync: AtomicUsize::new(self.wait_list_sync.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PartialEq for CommandQueue {
+    fn eq(&self, other: &Self) -> bool {
+        self.queue == other.queue
+    }
+}
+
+impl Eq for CommandQueue { }
+
+impl Drop for CommandQueue {
+    fn drop(&mut self) {
+        catch_ffi(unsafe { ffi::clReleaseCommandQueue(self.queue) }).unwrap();
+    }
+}
+
+impl fmt::Debug for CommandQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CommandQueue")
+         .field("context", &self.get_info::<information::Context>())
+         .field("device", &self.get_info::<information::Device>())
+         .finish()
+    }
+}
+
+#[test]
+fn test_relation_to_context_and_device() {
+    let context = Context::default().unwrap();
+    let device = Device::default().unwrap();
+    let queue = CommandQueue::create(&context, &device, Properties::new()).unwrap();
+
+    assert_eq!(context, queue.get_info::<information::Context>());
+    assert_eq!(device, queue.get_info::<information::Device>());
+}
+
+#[test]
+fn test_relation_to_properties() {
+    let properties = PropertiesBuilder::new().profiling().finish();
+    let queue = CommandQueue::create(
+        &Context::default().unwrap(),
+        &Device::default().unwrap(),
+        properties.clone()
+    );
+    
+    // In case `CL_QUEUE_PROFILING_ENABLE` is not supported...
+    if let Ok(queue) = queue {
+        assert_eq!(properties, queue.get_info::<information::Properties>());
+    }
+}
+
+#[test]
+fn test_wait_list() {
+    use wrapper::types::program;
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<information::Context>();
+    let device = queue.get_info::<information::Device>();
+    let program = program::Builder::create_with_sources(Some("__kernel void nop() { }"), &context)
+        .unwrap();
+    let program = program.build().wait().unwrap();
+    let kernel = program.create_kernel("nop").unwrap();
+    let range = NdRange::new(&[1]);
+
+    let first = queue.enqueue_nd_range(&kernel, &range).unwrap();
+    let second = queue.enqueue_nd_range(&kernel, &range).unwrap();
+    let last = queue.after(&[&first, &second]).enqueue_nd_range(&kernel, &range).unwrap();
+    assert_eq!(last.wait(), Ok(()));
+
+    let other_context = Context::create(Some(&device), ::wrapper::types::context::Properties::new())
+        .unwrap();
+    let other_queue = CommandQueue::create(&other_context, &device, Properties::new()).unwrap();
+    let other_program = program::Builder::create_with_sources(
+        Some("__kernel void nop() { }"),
+        &other_context
+    ).unwrap();
+    let other_program = other_program.build().wait().unwrap();
+    let other = other_queue.enqueue_nd_range(&other_program.create_kernel("nop").unwrap(), &range)
+        .unwrap();
+
+    assert_eq!(
+        queue.after(&[&first, &other]).enqueue_nd_range(&kernel, &range).unwrap_err(),
+        LaunchError::WaitList(WaitListError::ContextMismatch { index: 1 })
+    );
+
+    let gate = event::UserEvent::create(&context);
+    let failed = (*gate).clone();
+    gate.abort(-42);
+    assert_eq!(
+        queue.after(&[&first, &failed]).enqueue_nd_range(&kernel, &range).unwrap_err(),
+        LaunchError::WaitList(WaitListError::FailedDependency { index: 1, status: -42 })
+    );
+}
+
+#[test]
+fn test_synchronization() {
+    use wrapper::types::program;
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<information::Context>();
+    let program = program::Builder::create_with_sources(Some("__kernel void nop() { }"), &context)
+        .unwrap();
+    let program = program.build().wait().unwrap();
+    let kernel = program.create_kernel("nop").unwrap();
+    let range = NdRange::new(&[1]);
+
+    let first = queue.enqueue_nd_range(&kernel, &range).unwrap();
+    let marker = queue.after(&[&first]).enqueue_marker().unwrap();
+    let barrier = queue.enqueue_barrier();
+    let last = queue.enqueue_nd_range(&kernel, &range).unwrap();
+    queue.flush();
+    queue.finish();
+
+    for event in &[first, marker, barrier, last] {
+        assert_eq!(event.status(), event::Status::Complete);
+    }
+    assert_eq!(queue.enqueue_marker().wait(), Ok(()));
+}
+
+#[test]
+fn test_native() {
+    use wrapper::types::mem;
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<information::Context>();
+    let device = queue.get_info::<information::Device>();
+    let a = Buffer::create(vec![1i32, 2, 3], &context, mem::Flags::new()).unwrap();
+    let b = Buffer::create(vec![0i32; 3], &context, mem::Flags::new()).unwrap();
+
+    let add = |data: &mut [&mut [i32]]| {
+        let (a, b) = data.split_at_mut(1);
+        for (x, y) in a[0].iter().zip(b[0].iter_mut()) {
+            *y = 2 * *x;
+        }
+    };
+
+    if !device.get_info::<device::information::ExecutionCapabilities>().native_kernel() {
+        assert_eq!(queue.enqueue_native(add, &[&a, &b]).unwrap_err(), NativeError::NotSupported);
+        return;
+    }
+
+    assert_eq!(queue.enqueue_native(add, &[&a, &a]).unwrap_err(), NativeError::AliasedBuffers);
+    queue.enqueue_native(add, &[&a, &b]).unwrap().wait().unwrap();
+    let mut result = [0; 3];
+    queue.enqueue_read_buffer(&b, 0, &mut result).unwrap();
+    assert_eq!(result, [2, 4, 6]);
+}
+
+#[test]
+fn test_task() {
+    use wrapper::types::program;
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<information::Context>();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void answer(int offset, __global int * result) {
+            *result = 40 + offset;
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+    let mut kernel = program.create_kernel("answer").unwrap();
+
+    assert_eq!(
+        queue.enqueue_task(&kernel).unwrap_err(),
+        LaunchError::InvalidKernelArgs
+    );
+    kernel.set_arg(0, &2i32).unwrap();
+    assert_eq!(queue.run_scalar_task::<i32>(&mut kernel, 1), Ok(42));
+
+    // The result buffer has been released, hence the argument is unset.
+    match kernel.validate_args() {
+        Err(err) =>
+            assert_eq!(err.errors(), &[kernel::arg::ArgError::Unset { index: 1, name: None }]),
+        Ok(()) => panic!("the result argument should be unset"),
+    }
+}
+
+#[test]
+fn test_region() {
+    let region = Region3::new([2, 3, 2]);
+    assert_eq!((region.row_pitch(), region.slice_pitch()), (2, 6));
+    assert_eq!(region.rows().unwrap().collect::<Vec<_>>(), vec![0, 2, 4, 6, 8, 10]);
+    assert_eq!(region.end(), Ok(12));
+
+    let region = region.set_origin([1, 1, 0]).set_row_pitch(4).set_slice_pitch(16);
+    assert_eq!(region.rows().unwrap().collect::<Vec<_>>(), vec![5, 9, 13, 21, 25, 29]);
+    assert_eq!(region.end(), Ok(31));
+    assert!(region.is_valid());
+    assert!(!region.set_row_pitch(1).is_valid());
+    assert!(!Region3::new([0, 1, 1]).is_valid());
+
+    // Two columns of a 4x4 image.
+    let left = Region3::new([2, 4, 1]).set_row_pitch(4);
+    let right = left.set_origin([2, 0, 0]);
+    assert_eq!(overlap(&left, &right), Ok(false));
+    assert_eq!(overlap(&left, &right.set_origin([1, 3, 0])), Ok(true));
+    assert_eq!(overlap(&left, &left), Ok(true));
+
+    assert_eq!(check_regions(&left, 16, &right, 16), Ok(()));
+    let shifted = right.set_origin([2, 1, 0]);
+    assert_eq!(check_regions(&left, 16, &shifted, 16), Err(TransferError::OutOfBounds));
+    let smaller = Region3::new([2, 3, 1]);
+    assert_eq!(check_regions(&left, 16, &smaller, 16), Err(TransferError::InvalidRegion));
+
+    // Regions whose end overflows are out of bounds.
+    let huge = Region3::new([2, 4, 1]).set_origin([0, 0, usize::MAX]);
+    assert_eq!(check_regions(&huge, 16, &left, 16), Err(TransferError::OutOfBounds));
+    let huge = Region3::new([2, 4, 2]).set_row_pitch(usize::MAX / 2);
+    assert!(huge.is_valid());
+    assert_eq!(check_regions(&huge, 16, &Region3::new([2, 4, 2]), 16),
+               Err(TransferError::OutOfBounds));
+}
+
+#[test]
+fn test_transfers() {
+    use wrapper::types::mem;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<information::Context>();
+    let buffer = Buffer::create(vec![0i32; 16], &context, mem::Flags::new()).unwrap();
+    let other = Buffer::create(vec![0i32; 16], &context, mem::Flags::new()).unwrap();
+
+    queue.enqueue_fill_buffer(&buffer, 7, 4, 8).unwrap();
+    let bytes = Buffer::create(vec![[0u8; 3]; 4], &context, mem::Flags::new()).unwrap();
+    let error = queue.enqueue_fill_buffer(&bytes, [1; 3], 0, 4).unwrap_err();
+    assert_eq!(error, TransferError::InvalidPattern);
+    let error = queue.enqueue_copy_buffer(&buffer, 0, &buffer, 2, 4).unwrap_err();
+    assert_eq!(error, TransferError::Overlap);
+
+    let host: Vec<i32> = (0..16).collect();
+    let quarter = Region3::new([2, 2, 1]).set_row_pitch(4);
+    queue.enqueue_write_buffer_rect(&other, &quarter, &host, &quarter.set_origin([2, 2, 0]))
+         .unwrap();
+    queue.enqueue_copy_buffer_rect(&other, &quarter, &buffer, &quarter.set_origin([2, 0, 0]))
+         .unwrap();
+    let shifted = quarter.set_origin([1, 1, 0]);
+    let error = queue.enqueue_copy_buffer_rect(&buffer, &quarter, &buffer, &shifted).unwrap_err();
+    assert_eq!(error, TransferError::Overlap);
+
+    let mut result = [0; 16];
+    queue.enqueue_read_buffer(&buffer, 0, &mut result).unwrap();
+    assert_eq!(result, [0, 0, 10, 11, 7, 7, 14, 15, 7, 7, 7, 7, 0, 0, 0, 0]);
+
+    let mut column = [0; 4];
+    let region = Region3::new([1, 4, 1]).set_origin([3, 0, 0]).set_row_pitch(4);
+    queue.enqueue_read_buffer_rect(&buffer, &region, &mut column, &Region3::new([1, 4, 1]))
+         .unwrap();
+    assert_eq!(column, [11, 15, 7, 0]);
+}
+
+#[test]
+fn test_migrate() {
+    use wrapper::types::mem;
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<information::Context>();
+    let buffer = Buffer::create(vec![1i32; 16], &context, mem::Flags::new()).unwrap();
+    let other = Buffer::create(vec![2.0f32; 16], &context, mem::Flags::new()).unwrap();
+
+    let to_host = MigrationFlagsBuilder::new().host().finish();
+    assert!(to_host.host() && !to_host.content_undefined());
+    queue.migrate(&[&buffer, &other], to_host).unwrap().wait().unwrap();
+    queue.migrate(&[&buffer, &other], MigrationFlags::new()).unwrap().wait().unwrap();
+
+    let mut data = [0; 16];
+    queue.enqueue_read_buffer(&buffer, 0, &mut data).unwrap();
+    assert_eq!(data, [1; 16]);
+
+    assert!(queue.migrate(&[], MigrationFlags::new()).is_ok());
 }
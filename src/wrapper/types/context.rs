@@ -4,6 +4,7 @@ use wrapper::ffi;
 use wrapper::information::InformationResult;
 use wrapper::types::platform::Platform;
 use wrapper::types::device::Device;
+use wrapper::types::accounting;
 use errors::*;
 use std::iter::IntoIterator;
 use std::fmt;
@@ -137,6 +138,80 @@ impl InformationResult<usize> for Properties {
     }
 }
 
+/// A snapshot of the memory allocated through this crate in a context or on a device, returned
+/// by `Context::memory_usage` and `Device::memory_usage`. Only memory objects created through
+/// this crate are accounted for, and a memory object counts until the runtime actually frees it.
+/// A memory object of a context holding several devices counts on each of them.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct MemoryUsage {
+    allocated: usize,
+    peak: usize,
+    objects: usize,
+    budget: Option<usize>,
+}
+
+impl MemoryUsage {
+    /// Return the number of bytes currently allocated.
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    /// Return the highest number of bytes allocated at once.
+    pub fn peak(&self) -> usize {
+        self.peak
+    }
+
+    /// Return the number of live memory objects.
+    pub fn objects(&self) -> usize {
+        self.objects
+    }
+
+    /// Return the budget in bytes if any, see `Context::set_memory_budget`. Always `None` for a
+    /// device.
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Return the number of bytes which can still be allocated before exceeding the budget if
+    /// any.
+    pub fn available(&self) -> Option<usize> {
+        self.budget.map(|budget| budget.saturating_sub(self.allocated))
+    }
+
+    pub(super) fn reserve(&mut self, size: usize) -> bool {
+        let allocated = self.allocated + size;
+        match self.budget {
+            Some(budget) if allocated > budget => return false,
+            _ => (),
+        }
+
+        self.allocated = allocated;
+        self.peak = self.peak.max(allocated);
+        self.objects += 1;
+        true
+    }
+
+    pub(super) fn release(&mut self, size: usize) {
+        self.allocated -= size;
+        self.objects -= 1;
+    }
+
+    pub(super) fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+}
+
+/// A memory budget for a context, see `Context::set_memory_budget`.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Budget {
+    /// A number of bytes.
+    Bytes(usize),
+
+    /// A fraction of the global memory size of the smallest device of the context
+    /// (`device::information::GlobalMemSize`).
+    Fraction(f64),
+}
+
 /// `Context` is a high-level type which maps to the low-level `cl_context` OpenCL type.
 /// An object of type `Context` acts as a ref-counted reference to an OpenCL context.
 #[derive(PartialEq, Eq)]
@@ -187,9 +262,11 @@ impl Context {
             catch_ffi(ffi::clRetainContext(context)).unwrap();
         }
 
-        Context {
+        let context = Context {
             context,
-        }
+        };
+        accounting::retain_handle(&context);
+        context
     }
 
     pub(super) unsafe fn underlying(&self) -> ffi::cl_context {
@@ -274,7 +351,7 @@ impl Context {
         }
 
         // Other errors will cause panic.
-        let result = catch_ffi(error).map(|()| Context { context });
+        let result = catch_ffi(error).map(|()| unsafe { Context::from_ffi(context, false) });
         Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
     }
 
@@ -289,6 +366,75 @@ impl Context {
         Device::default().and_then(|d| Context::create(Some(&d), Properties::new()).ok())
     }
 
+    /// Return a snapshot of the memory currently allocated in the context through this crate.
+    /// The usage and the budget of a context are kept as long as a `Context` handle to it or a
+    /// memory object allocated in it is alive.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{Context, Buffer, mem};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let buffer = Buffer::create(vec![0u8; 1024], &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    ///
+    /// let usage = context.memory_usage();
+    /// assert_eq!(usage.allocated(), 1024);
+    /// assert_eq!(usage.objects(), 1);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage {
+        accounting::usage(self)
+    }
+
+    /// Set or clear the memory budget of the context. Once a budget is set, creating a memory
+    /// object which would make the memory allocated through this crate exceed the budget fails
+    /// with an `AllocationFailure` error, before calling the driver. Memory objects already
+    /// allocated are left untouched, even if they exceed the new budget.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{Context, Buffer, mem};
+    /// use gprust::context::Budget;
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// context.set_memory_budget(Some(Budget::Bytes(1024)));
+    ///
+    /// let buffer = Buffer::create(vec![0u8; 2048], &context, mem::Flags::new());
+    /// assert_eq!(buffer.err(), Some(mem::CreationError::AllocationFailure));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Panics
+    /// Panic if a `Budget::Fraction` is not positive, or same as `get_info`.
+    pub fn set_memory_budget(&self, budget: Option<Budget>) {
+        use wrapper::types::device::information::GlobalMemSize;
+
+        let budget = budget.map(|budget| match budget {
+            Budget::Bytes(bytes) => bytes,
+            Budget::Fraction(fraction) => {
+                assert!(fraction > 0.0, "budget fraction should be positive");
+
+                let global_mem_size = self.get_info::<information::Devices>()
+                                          .iter()
+                                          .map(|d| d.get_info::<GlobalMemSize>())
+                                          .min()
+                                          .unwrap_or(0);
+                (global_mem_size as f64 * fraction) as usize
+            }
+        });
+
+        accounting::set_budget(self, budget);
+    }
+
     /// Query an information to the context. `T` should be a marker type from the `information`
     /// module.
     ///
@@ -338,14 +484,17 @@ impl Clone for Context {
     fn clone(&self) -> Self {
         catch_ffi(unsafe { ffi::clRetainContext(self.context) }).unwrap();
 
-        Context {
+        let context = Context {
             context: self.context,
-        }
+        };
+        accounting::retain_handle(&context);
+        context
     }
 }
 
 impl Drop for Context {
     fn drop(&mut self) {
+        accounting::release_handle(self);
         catch_ffi(unsafe { ffi::clReleaseContext(self.context) }).unwrap();
     }
 }
@@ -401,3 +550,41 @@ fn test_relation_to_properties() {
         );
     }
 }
+
+#[test]
+fn test_memory_budget() {
+    use wrapper::types::mem::{self, Buffer};
+    use std::time::{Duration, Instant};
+    use std::thread;
+
+    let context = Context::default().unwrap();
+    let device = context.get_info::<information::Devices>().pop().unwrap();
+    assert_eq!(context.memory_usage(), MemoryUsage::default());
+
+    context.set_memory_budget(Some(Budget::Bytes(1024)));
+    let buffer = Buffer::create(vec![0u8; 768], &context, mem::Flags::new()).unwrap();
+    let usage = context.memory_usage();
+    assert_eq!((usage.allocated(), usage.objects()), (768, 1));
+    assert_eq!(usage.available(), Some(256));
+    assert!(device.memory_usage().allocated() >= 768);
+
+    let result = Buffer::create(vec![0u8; 512], &context, mem::Flags::new());
+    assert_eq!(result.err(), Some(mem::CreationError::AllocationFailure));
+    assert_eq!(context.memory_usage().objects(), 1);
+
+    // The memory is given back once the runtime has destroyed the buffer, which may happen on
+    // another thread.
+    drop(buffer);
+    let start = Instant::now();
+    while context.memory_usage().allocated() != 0 {
+        assert!(start.elapsed() < Duration::from_secs(5), "the memory was not released");
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(context.memory_usage().peak(), 768);
+    assert!(Buffer::create(vec![0u8; 1024], &context, mem::Flags::new()).is_ok());
+
+    context.set_memory_budget(Some(Budget::Fraction(0.5)));
+    assert!(context.memory_usage().budget().unwrap() > 0);
+    context.set_memory_budget(None);
+    assert_eq!(context.memory_usage().available(), None);
+}
@@ -4,6 +4,8 @@ pub mod information;
 
 use wrapper::ffi;
 use wrapper::information::*;
+use wrapper::types::accounting;
+use wrapper::types::context::MemoryUsage;
 use errors::*;
 use std::fmt;
 use std::iter::IntoIterator;
@@ -454,6 +456,12 @@ impl Device {
             .collect()
     }
 
+    /// Return a snapshot of the memory currently allocated through this crate in all the
+    /// contexts holding the device, see `Context::memory_usage`.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        accounting::device_usage(self)
+    }
+
     /// Query an information to the device. `T` should be a marker type from the `information`
     /// module.
     ///
@@ -524,6 +532,27 @@ impl fmt::Debug for Device {
     }
 }
 
+/// Parse the version out of a version string reported by a device, which has the form
+/// `<prefix><major>.<minor> <vendor-specific information>`, e.g. `prefix` is `"OpenCL "` for
+/// `information::Version` and `"OpenCL C "` for `information::OpenClCVersion`.
+pub(super) fn parse_version(version: &str, prefix: &str) -> Option<(u32, u32)> {
+    let version = version.trim().strip_prefix(prefix)?;
+    let version = version.split_whitespace().next()?;
+    let mut numbers = version.split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[test]
+fn test_parse_version() {
+    assert_eq!(parse_version("OpenCL 1.1 CUDA", "OpenCL "), Some((1, 1)));
+    assert_eq!(parse_version("OpenCL 3.0 ", "OpenCL "), Some((3, 0)));
+    assert_eq!(parse_version("OpenCL C 1.2", "OpenCL "), None);
+    assert_eq!(parse_version("OpenCL C 1.2 pocl", "OpenCL C "), Some((1, 2)));
+    assert_eq!(parse_version("OpenCL 1.2", "OpenCL C "), None);
+}
+
 #[test]
 fn test_relation_to_platform() {
     use wrapper::types::platform::{self, Platform};
@@ -0,0 +1,695 @@
+//! A module defining the `cl_event` related types, such as the high-level `Event` type.
+
+use wrapper::ffi;
+use wrapper::information::InformationResult;
+use wrapper::types::context::Context;
+use errors::*;
+use futures::{Future, Poll, Async};
+use futures::task::AtomicTask;
+use std::os::raw::c_void;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, panic};
+
+enumz!(
+    CommandType,
+    ffi::cl_command_type,
+    "cl_command_type",
+    NdRangeKernel => [ffi::CL_COMMAND_NDRANGE_KERNEL, "CL_COMMAND_NDRANGE_KERNEL"],
+    Task => [ffi::CL_COMMAND_TASK, "CL_COMMAND_TASK"],
+    NativeKernel => [ffi::CL_COMMAND_NATIVE_KERNEL, "CL_COMMAND_NATIVE_KERNEL"],
+    ReadBuffer => [ffi::CL_COMMAND_READ_BUFFER, "CL_COMMAND_READ_BUFFER"],
+    WriteBuffer => [ffi::CL_COMMAND_WRITE_BUFFER, "CL_COMMAND_WRITE_BUFFER"],
+    CopyBuffer => [ffi::CL_COMMAND_COPY_BUFFER, "CL_COMMAND_COPY_BUFFER"],
+    ReadImage => [ffi::CL_COMMAND_READ_IMAGE, "CL_COMMAND_READ_IMAGE"],
+    WriteImage => [ffi::CL_COMMAND_WRITE_IMAGE, "CL_COMMAND_WRITE_IMAGE"],
+    CopyImage => [ffi::CL_COMMAND_COPY_IMAGE, "CL_COMMAND_COPY_IMAGE"],
+    CopyImageToBuffer => [ffi::CL_COMMAND_COPY_IMAGE_TO_BUFFER, "CL_COMMAND_COPY_IMAGE_TO_BUFFER"],
+    CopyBufferToImage => [ffi::CL_COMMAND_COPY_BUFFER_TO_IMAGE, "CL_COMMAND_COPY_BUFFER_TO_IMAGE"],
+    MapBuffer => [ffi::CL_COMMAND_MAP_BUFFER, "CL_COMMAND_MAP_BUFFER"],
+    MapImage => [ffi::CL_COMMAND_MAP_IMAGE, "CL_COMMAND_MAP_IMAGE"],
+    UnmapMemObject => [ffi::CL_COMMAND_UNMAP_MEM_OBJECT, "CL_COMMAND_UNMAP_MEM_OBJECT"],
+    Marker => [ffi::CL_COMMAND_MARKER, "CL_COMMAND_MARKER"],
+    AcquireGlObjects => [ffi::CL_COMMAND_ACQUIRE_GL_OBJECTS, "CL_COMMAND_ACQUIRE_GL_OBJECTS"],
+    ReleaseGlObjects => [ffi::CL_COMMAND_RELEASE_GL_OBJECTS, "CL_COMMAND_RELEASE_GL_OBJECTS"],
+    ReadBufferRect => [ffi::CL_COMMAND_READ_BUFFER_RECT, "CL_COMMAND_READ_BUFFER_RECT"],
+    WriteBufferRect => [ffi::CL_COMMAND_WRITE_BUFFER_RECT, "CL_COMMAND_WRITE_BUFFER_RECT"],
+    CopyBufferRect => [ffi::CL_COMMAND_COPY_BUFFER_RECT, "CL_COMMAND_COPY_BUFFER_RECT"],
+    User => [ffi::CL_COMMAND_USER, "CL_COMMAND_USER"],
+    Barrier => [ffi::CL_COMMAND_BARRIER, "CL_COMMAND_BARRIER"],
+    MigrateMemObjects => [ffi::CL_COMMAND_MIGRATE_MEM_OBJECTS, "CL_COMMAND_MIGRATE_MEM_OBJECTS"],
+    FillBuffer => [ffi::CL_COMMAND_FILL_BUFFER, "CL_COMMAND_FILL_BUFFER"],
+    FillImage => [ffi::CL_COMMAND_FILL_IMAGE, "CL_COMMAND_FILL_IMAGE"]
+);
+
+/// Execution status of the command associated with an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// The command has been enqueued in the command queue (`CL_QUEUED`).
+    Queued,
+
+    /// The command has been submitted by the host to the device (`CL_SUBMITTED`).
+    Submitted,
+
+    /// The device is currently executing the command (`CL_RUNNING`).
+    Running,
+
+    /// The command has completed (`CL_COMPLETE`).
+    Complete,
+
+    /// `Error(code)` where `code` is the negative integer with which the command was abnormally
+    /// terminated.
+    Error(ffi::cl_int),
+}
+
+impl InformationResult<usize> for Status {
+    type Item = ffi::cl_int;
+
+    unsafe fn get_info<F>(function: F) -> Result<Self, RawError>
+        where F: Fn(usize, *mut Self::Item, *mut usize) -> ffi::cl_int
+    {
+        let status: ffi::cl_int = InformationResult::get_info(function)?;
+        Ok(match status {
+            ffi::CL_QUEUED => Status::Queued,
+            ffi::CL_SUBMITTED => Status::Submitted,
+            ffi::CL_RUNNING => Status::Running,
+            ffi::CL_COMPLETE => Status::Complete,
+            code if code < 0 => Status::Error(code),
+            other => panic!("unexpected execution status: {}", other),
+        })
+    }
+}
+
+pub mod information {
+    //! A module containing the information marker types for `Event`.
+
+    use wrapper::ffi;
+    use wrapper::information::*;
+    use wrapper::types::{context, command_queue};
+
+    /// A trait implemented by marker types for retrieving information through `clGetEventInfo`.
+    pub trait EventInformation: Information<ffi::cl_event_info> { }
+
+    macro_rules! info_impl {
+        ($type: ident, $result: ty, $id: expr, $id_name: expr, $test_fun: ident) => {
+            general_info_impl!(EventInformation, ffi::cl_event_info, $type, $result, $id, $id_name);
+
+            #[test]
+            fn $test_fun() {
+                let queue = command_queue::CommandQueue::default().unwrap();
+                let event = super::test_event(&queue);
+                let _ = event.get_info::<$type>();
+            }
+        };
+    }
+
+    info_impl!(CommandQueue, command_queue::CommandQueue, ffi::CL_EVENT_COMMAND_QUEUE, "CL_EVENT_COMMAND_QUEUE", test_command_queue);
+    info_impl!(Context, context::Context, ffi::CL_EVENT_CONTEXT, "CL_EVENT_CONTEXT", test_context);
+    info_impl!(CommandType, super::CommandType, ffi::CL_EVENT_COMMAND_TYPE, "CL_EVENT_COMMAND_TYPE", test_command_type);
+    info_impl!(CommandExecutionStatus, super::Status, ffi::CL_EVENT_COMMAND_EXECUTION_STATUS, "CL_EVENT_COMMAND_EXECUTION_STATUS", test_command_execution_status);
+    info_impl!(ReferenceCount, ffi::cl_uint, ffi::CL_EVENT_REFERENCE_COUNT, "CL_EVENT_REFERENCE_COUNT", test_reference_count);
+}
+
+/// `Event` is a high-level type which maps to the low-level `cl_event` OpenCL type.
+/// An object of type `Event` acts as a ref-counted reference to an OpenCL event, and is returned
+/// by every enqueue operation of `CommandQueue`.
+///
+/// `Event` implements `Future`, resolving once the associated command has completed.
+pub struct Event {
+    event: ffi::cl_event,
+
+    // Set once a completion callback has been registered by `poll`, for waking up the task
+    // which polled the event last.
+    task: Option<Arc<AtomicTask>>,
+}
+
+unsafe impl Send for Event { }
+unsafe impl Sync for Event { }
+
+/// `ExecutionError(code)` is returned when a command was abnormally terminated, where `code` is
+/// the negative execution status of the command.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct ExecutionError(pub ffi::cl_int);
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "command terminated with execution status {}", self.0)
+    }
+}
+
+/// An error returned by `Event::profile`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ProfilingError {
+    /// Profiling information is not available, because the event is a user event or because
+    /// the command queue was not created with the `profiling` property.
+    NotAvailable,
+
+    /// The command has not completed yet.
+    NotComplete,
+
+    /// The command was abnormally terminated.
+    Terminated(ExecutionError),
+}
+
+impl fmt::Display for ProfilingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProfilingError::NotAvailable =>
+                write!(f, "profiling information is not available"),
+            ProfilingError::NotComplete =>
+                write!(f, "the command has not completed yet"),
+            ProfilingError::Terminated(err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+/// The profiling timestamps of a command, as returned by `Event::profile`. Timestamps are
+/// device time counters in nanoseconds, whose resolution is given by
+/// `device::information::ProfilingTimerResolution`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Profile {
+    queued: ffi::cl_ulong,
+    submit: ffi::cl_ulong,
+    start: ffi::cl_ulong,
+    end: ffi::cl_ulong,
+}
+
+impl Profile {
+    /// Return the time at which the command was enqueued by the host.
+    pub fn queued(&self) -> ffi::cl_ulong {
+        self.queued
+    }
+
+    /// Return the time at which the command was submitted to the device.
+    pub fn submit(&self) -> ffi::cl_ulong {
+        self.submit
+    }
+
+    /// Return the time at which the command started executing.
+    pub fn start(&self) -> ffi::cl_ulong {
+        self.start
+    }
+
+    /// Return the time at which the command finished executing.
+    pub fn end(&self) -> ffi::cl_ulong {
+        self.end
+    }
+
+    /// Return the time elapsed between the command being enqueued and starting to execute.
+    pub fn queue_latency(&self) -> Duration {
+        Duration::from_nanos(self.start.saturating_sub(self.queued))
+    }
+
+    /// Return the execution time of the command.
+    pub fn execution_time(&self) -> Duration {
+        Duration::from_nanos(self.end.saturating_sub(self.start))
+    }
+}
+
+impl Event {
+    pub(super) unsafe fn from_ffi(event: ffi::cl_event, retain: bool) -> Self {
+        if retain {
+            catch_ffi(ffi::clRetainEvent(event)).unwrap();
+        }
+
+        Event {
+            event,
+            task: None,
+        }
+    }
+
+    pub(super) unsafe fn underlying(&self) -> ffi::cl_event {
+        self.event
+    }
+
+    /// Query an information to the event. `T` should be a marker type from the `information`
+    /// module.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources, or if an invalid information
+    /// param is passed (should only happen when a user incorrectly implements
+    /// `EventInformation` on their own, otherwise it is a bug).
+    pub fn get_info<T: information::EventInformation>(&self) -> T::Result {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetEventInfo(
+                    self.event,
+                    T::id(),
+                    size,
+                    value as _,
+                    ret_size
+                )
+            })
+        };
+
+        expect!(
+            result,
+            ffi::CL_OUT_OF_RESOURCES,
+            ffi::CL_OUT_OF_HOST_MEMORY,
+            ffi::CL_INVALID_VALUE
+        )
+    }
+
+    /// Return the execution status of the associated command.
+    pub fn status(&self) -> Status {
+        self.get_info::<information::CommandExecutionStatus>()
+    }
+
+    // Query a device time counter in nanoseconds. Return `None` if profiling information is
+    // not available, i.e. the queue does not have profiling enabled or the command has not
+    // completed yet.
+    fn profiling_info(&self, param: ffi::cl_profiling_info) -> Option<ffi::cl_ulong> {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetEventProfilingInfo(self.event, param, size, value as _, ret_size)
+            })
+        };
+
+        match result {
+            Err(RawError(ffi::CL_PROFILING_INFO_NOT_AVAILABLE)) => None,
+            result => Some(expect!(
+                result,
+                ffi::CL_OUT_OF_RESOURCES,
+                ffi::CL_OUT_OF_HOST_MEMORY,
+                ffi::CL_INVALID_VALUE
+            )),
+        }
+    }
+
+    /// Return the profiling timestamps of the associated command.
+    ///
+    /// # Errors
+    /// * `ProfilingError::NotAvailable` for a user event, or if the command queue was not
+    /// created with the `profiling` property.
+    /// * `ProfilingError::NotComplete` if the command has not completed yet.
+    /// * `ProfilingError::Terminated(err)` if the command was abnormally terminated.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn profile(&self) -> Result<Profile, ProfilingError> {
+        if self.get_info::<information::CommandType>() == CommandType::User {
+            return Err(ProfilingError::NotAvailable);
+        }
+
+        match self.status() {
+            Status::Complete => (),
+            Status::Error(code) => return Err(ProfilingError::Terminated(ExecutionError(code))),
+            _ => return Err(ProfilingError::NotComplete),
+        }
+
+        let timestamps = (
+            self.profiling_info(ffi::CL_PROFILING_COMMAND_QUEUED),
+            self.profiling_info(ffi::CL_PROFILING_COMMAND_SUBMIT),
+            self.profiling_info(ffi::CL_PROFILING_COMMAND_START),
+            self.profiling_info(ffi::CL_PROFILING_COMMAND_END),
+        );
+
+        match timestamps {
+            (Some(queued), Some(submit), Some(start), Some(end)) =>
+                Ok(Profile { queued, submit, start, end }),
+            _ => Err(ProfilingError::NotAvailable),
+        }
+    }
+
+    // Call `callback` once the associated command has completed or was abnormally terminated,
+    // e.g. for releasing host memory used by the command. If the callback cannot be
+    // registered, block until then and call it immediately.
+    pub(super) fn on_complete<F: FnOnce() + Send + 'static>(&self, callback: F) {
+        let callback: Box<Box<dyn FnOnce() + Send>> = Box::new(Box::new(callback));
+        let user_data = Box::into_raw(callback);
+        let error = unsafe {
+            ffi::clSetEventCallback(self.event, ffi::CL_COMPLETE, Some(call), user_data as _)
+        };
+
+        if error != ffi::CL_SUCCESS {
+            let callback = unsafe { Box::from_raw(user_data) };
+            let _ = self.wait_for_completion();
+            callback();
+        }
+    }
+
+    /// Block until the associated command has completed. Unlike `Future::wait`, this does not
+    /// consume the event.
+    ///
+    /// For a user event, this blocks until another thread signals it through `UserEvent`,
+    /// i.e. forever if none does.
+    ///
+    /// # Errors
+    /// `ExecutionError(code)` if the command was abnormally terminated.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn wait_for_completion(&self) -> Result<(), ExecutionError> {
+        let error = unsafe { ffi::clWaitForEvents(1, &self.event) };
+
+        if error != ffi::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST {
+            expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+        }
+
+        match self.status() {
+            Status::Error(code) => Err(ExecutionError(code)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Wake up the task registered in the `AtomicTask` boxed in `user_data`.
+unsafe extern "C" fn notify(_: ffi::cl_event, _: ffi::cl_int, user_data: *mut c_void) {
+    Box::from_raw(user_data as *mut Arc<AtomicTask>).notify();
+}
+
+unsafe extern "C" fn call(_: ffi::cl_event, _: ffi::cl_int, user_data: *mut c_void) {
+    call_boxed(user_data);
+}
+
+// Call the callback boxed in `user_data`, as registered by a runtime callback.
+pub(super) unsafe fn call_boxed(user_data: *mut c_void) {
+    let callback = Box::from_raw(user_data as *mut Box<dyn FnOnce() + Send>);
+
+    // Unwinding through the runtime is not allowed.
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(callback));
+}
+
+impl Future for Event {
+    type Item = ();
+    type Error = ExecutionError;
+
+    fn poll(&mut self) -> Poll<(), ExecutionError> {
+        // Register the current task before checking the status, so that a completion in
+        // between is not missed.
+        if let Some(ref task) = self.task {
+            task.register();
+        }
+
+        match self.status() {
+            Status::Complete => return Ok(Async::Ready(())),
+            Status::Error(code) => return Err(ExecutionError(code)),
+            _ => (),
+        }
+
+        if self.task.is_none() {
+            let task = Arc::new(AtomicTask::new());
+            task.register();
+
+            // The callback is called immediately if the command has completed in the meantime.
+            let user_data = Box::into_raw(Box::new(task.clone()));
+            let error = unsafe {
+                ffi::clSetEventCallback(self.event, ffi::CL_COMPLETE, Some(notify), user_data as _)
+            };
+
+            if error != ffi::CL_SUCCESS {
+                drop(unsafe { Box::from_raw(user_data) });
+            }
+            expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+            self.task = Some(task);
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.event == other.event
+    }
+}
+
+impl Eq for Event { }
+
+impl Clone for Event {
+    fn clone(&self) -> Self {
+        catch_ffi(unsafe { ffi::clRetainEvent(self.event) }).unwrap();
+
+        Event {
+            event: self.event,
+            task: None,
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        catch_ffi(unsafe { ffi::clReleaseEvent(self.event) }).unwrap();
+    }
+}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Event")
+         .field("command_type", &self.get_info::<information::CommandType>())
+         .field("status", &self.status())
+         .finish()
+    }
+}
+
+/// The execution status given to a `UserEvent` dropped without being signalled.
+pub const USER_EVENT_DROPPED: ffi::cl_int = -1;
+
+/// A user event, i.e. an event whose status is set by the host rather than by a command. A
+/// `UserEvent` dereferences to `Event`, so that it can be used in a wait list to gate commands
+/// on host input: a pipeline can be enqueued up front and released with `UserEvent::complete`,
+/// or aborted with `UserEvent::abort`, in which case dependent commands are terminated too.
+///
+/// A `UserEvent` dropped without being signalled is aborted with status `USER_EVENT_DROPPED`,
+/// so that the commands waiting for it do not block the queue forever.
+///
+/// Note that blocking on the event itself, e.g. through `Event::wait_for_completion` or
+/// `Future::wait` on a clone, blocks forever if no other thread ever signals it.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{CommandQueue, program, Future};
+/// use gprust::command_queue::{self, NdRange};
+/// use gprust::event::UserEvent;
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let queue = CommandQueue::default().ok_or("no default command queue")?;
+/// let context = queue.get_info::<command_queue::information::Context>();
+/// let program = program::Builder::create_with_sources(
+///     Some("__kernel void process() { }"),
+///     &context
+/// ).expect("I did provide a source");
+/// let program = program.build().wait().map_err(|_| "build failed")?;
+/// let kernel = program.create_kernel("process").map_err(|_| "no such kernel")?;
+///
+/// let input_ready = UserEvent::create(&context);
+/// let event = queue.after(&[&input_ready])
+///                  .enqueue_nd_range(&kernel, &NdRange::new(&[16]))
+///                  .map_err(|_| "failed to enqueue kernel")?;
+///
+/// /* wait for host input */
+/// input_ready.complete();
+/// event.wait().map_err(|_| "kernel failed")?;
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+#[derive(PartialEq, Eq)]
+pub struct UserEvent {
+    event: Event,
+    signalled: bool,
+}
+
+impl UserEvent {
+    /// Create a user event associated with `context`, in the `Status::Submitted` state.
+    ///
+    /// # Panics
+    /// Panic if the host fails to allocate resources.
+    pub fn create(context: &Context) -> Self {
+        let mut error = 0;
+        let event = unsafe { ffi::clCreateUserEvent(context.underlying(), &mut error) };
+
+        // Errors will cause panic.
+        let result = catch_ffi(error).map(|()| unsafe { Event::from_ffi(event, false) });
+        UserEvent {
+            event: expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY),
+            signalled: false,
+        }
+    }
+
+    /// Mark the event as complete, releasing the commands waiting for it.
+    ///
+    /// # Panics
+    /// Panic if the host fails to allocate resources.
+    pub fn complete(mut self) {
+        self.set_status(ffi::CL_COMPLETE);
+    }
+
+    /// Abnormally terminate the event with the negative status `code`. The commands waiting
+    /// for it are terminated as well.
+    ///
+    /// # Panics
+    /// Panic if `code` is not negative or if the host fails to allocate resources.
+    pub fn abort(mut self, code: ffi::cl_int) {
+        assert!(code < 0, "the status of an aborted event must be negative");
+        self.set_status(code);
+    }
+
+    fn set_status(&mut self, status: ffi::cl_int) {
+        self.signalled = true;
+        let error = unsafe { ffi::clSetUserEventStatus(self.event.event, status) };
+        expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+    }
+}
+
+impl Deref for UserEvent {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+        &self.event
+    }
+}
+
+impl Drop for UserEvent {
+    fn drop(&mut self) {
+        if !self.signalled {
+            // Do not panic in drop, the event is released anyway.
+            let _ = unsafe { ffi::clSetUserEventStatus(self.event.event, USER_EVENT_DROPPED) };
+        }
+    }
+}
+
+impl fmt::Debug for UserEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("UserEvent")
+         .field(&self.event)
+         .finish()
+    }
+}
+
+// Enqueue a trivial kernel on `queue`, returning the associated event.
+#[cfg(test)]
+fn test_event(queue: &::wrapper::types::command_queue::CommandQueue) -> Event {
+    use wrapper::types::{program, command_queue};
+
+    let context = queue.get_info::<::wrapper::types::command_queue::information::Context>();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void nop() { }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+    let kernel = program.create_kernel("nop").unwrap();
+    queue.enqueue_nd_range(&kernel, &command_queue::NdRange::new(&[1])).unwrap()
+}
+
+#[test]
+fn test_wait() {
+    use wrapper::types::command_queue::CommandQueue;
+
+    let queue = CommandQueue::default().unwrap();
+    let event = test_event(&queue);
+    assert_eq!(event.wait_for_completion(), Ok(()));
+    assert_eq!(event.status(), Status::Complete);
+    assert_eq!(test_event(&queue).wait(), Ok(()));
+}
+
+#[test]
+fn test_poll() {
+    use wrapper::types::context;
+    use futures::executor::{self, Notify, NotifyHandle};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    struct Counter(AtomicUsize);
+
+    impl Notify for Counter {
+        fn notify(&self, _: usize) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let context = context::Context::default().unwrap();
+    let gate = UserEvent::create(&context);
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let handle = NotifyHandle::from(counter.clone());
+    let mut future = executor::spawn(gate.clone());
+
+    for _ in 0..3 {
+        assert_eq!(future.poll_future_notify(&handle, 0), Ok(Async::NotReady));
+    }
+
+    gate.complete();
+    let timeout = Duration::from_secs(5);
+    let start = ::std::time::Instant::now();
+    while counter.0.load(Ordering::SeqCst) == 0 && start.elapsed() < timeout {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    // Only one callback was registered for the three polls.
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert_eq!(future.poll_future_notify(&handle, 0), Ok(Async::Ready(())));
+}
+
+#[test]
+fn test_user_event() {
+    use wrapper::types::{command_queue, program};
+
+    let queue = command_queue::CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let program = program::Builder::create_with_sources(Some("__kernel void nop() { }"), &context)
+        .unwrap();
+    let program = program.build().wait().unwrap();
+    let kernel = program.create_kernel("nop").unwrap();
+    let range = command_queue::NdRange::new(&[1]);
+
+    let gate = UserEvent::create(&context);
+    assert_eq!(gate.status(), Status::Submitted);
+    let event = queue.after(&[&gate]).enqueue_nd_range(&kernel, &range).unwrap();
+    gate.complete();
+    assert_eq!(event.wait(), Ok(()));
+
+    // Cancellation propagates to dependent commands.
+    let gate = UserEvent::create(&context);
+    let event = queue.after(&[&gate]).enqueue_nd_range(&kernel, &range).unwrap();
+    gate.abort(-42);
+    assert!(event.wait_for_completion().is_err());
+
+    // So does dropping an unsignalled user event.
+    let gate = UserEvent::create(&context);
+    let event = queue.after(&[&gate]).enqueue_nd_range(&kernel, &range).unwrap();
+    drop(gate);
+    assert!(event.wait().is_err());
+}
+
+#[test]
+fn test_profile() {
+    use wrapper::types::{command_queue, context, device};
+
+    let context = context::Context::default().unwrap();
+    let device = device::Device::default().unwrap();
+    let properties = command_queue::PropertiesBuilder::new().profiling().finish();
+    let queue = command_queue::CommandQueue::create(&context, &device, properties).unwrap();
+
+    let event = test_event(&queue);
+    event.wait_for_completion().unwrap();
+    let profile = event.profile().unwrap();
+    assert!(profile.queued() <= profile.submit());
+    assert!(profile.submit() <= profile.start());
+    assert!(profile.start() <= profile.end());
+    assert_eq!(profile.execution_time().as_nanos() as ffi::cl_ulong, profile.end() - profile.start());
+
+    let queue = command_queue::CommandQueue::create(
+        &context,
+        &device,
+        command_queue::Properties::new()
+    ).unwrap();
+    let event = test_event(&queue);
+    event.wait_for_completion().unwrap();
+    assert_eq!(event.profile(), Err(ProfilingError::NotAvailable));
+    assert_eq!(UserEvent::create(&context).profile(), Err(ProfilingError::NotAvailable));
+
+    let queue = command_queue::CommandQueue::create(&context, &device, properties).unwrap();
+    let gate = UserEvent::create(&context);
+    let event = queue.after(&[&gate]).enqueue_marker().unwrap();
+    assert_eq!(event.profile(), Err(ProfilingError::NotComplete));
+    gate.abort(-42);
+    let _ = event.wait_for_completion();
+    match event.profile() {
+        Err(ProfilingError::Terminated(_)) => (),
+        other => panic!("unexpected profiling result: {:?}", other),
+    }
+}
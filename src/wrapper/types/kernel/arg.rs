@@ -0,0 +1,377 @@
+//! A module defining the types which can be passed as kernel arguments, and their validation
+//! against the argument information reported by the driver.
+
+use wrapper::ffi;
+use super::AddressQualifier;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::{fmt, mem, ptr};
+
+/// A trait for plain data types which have a counterpart in OpenCL C, and can thus be passed by
+/// value to kernels or stored in buffers.
+///
+/// # Safety
+/// The type must be `Copy`, contain no pointers, and have the same size and layout as the
+/// OpenCL C type named by `type_name`.
+pub unsafe trait Scalar: Copy + 'static {
+    /// Return the name of the OpenCL C type, as reported by
+    /// `kernel::information::TypeName` (e.g. `uint` for `u32`).
+    fn type_name() -> &'static str;
+}
+
+macro_rules! scalar_impl {
+    ($($type: ty => $name: expr),*) => {
+        $(
+        unsafe impl Scalar for $type {
+            fn type_name() -> &'static str {
+                $name
+            }
+        }
+        )*
+    };
+}
+
+scalar_impl!(
+    i8 => "char",
+    u8 => "uchar",
+    i16 => "short",
+    u16 => "ushort",
+    i32 => "int",
+    u32 => "uint",
+    i64 => "long",
+    u64 => "ulong",
+    f32 => "float",
+    f64 => "double"
+);
+
+// Names of the OpenCL C scalar types: only parameters of these types are checked, since
+// typedefs, structures or vector types cannot be related to a Rust type by name.
+const BUILTIN_TYPES: &[&str] = &[
+    "char", "uchar", "short", "ushort", "int", "uint", "long", "ulong", "float", "double",
+];
+
+/// Describe how an argument is passed to a kernel. Each variant holds the name of the OpenCL C
+/// (element) type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArgKind {
+    /// A value passed to a `__private` parameter.
+    Scalar(&'static str),
+
+    /// A memory object passed to a `__global` or `__constant` pointer.
+    Buffer(&'static str),
+
+    /// Local memory allocated for a `__local` pointer.
+    Local(&'static str),
+}
+
+impl fmt::Display for ArgKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgKind::Scalar(name) => write!(f, "scalar `{}`", name),
+            ArgKind::Buffer(name) => write!(f, "buffer of `{}`", name),
+            ArgKind::Local(name) => write!(f, "local memory of `{}`", name),
+        }
+    }
+}
+
+/// A trait for values which can be set as kernel arguments through `Kernel::set_arg`.
+///
+/// # Safety
+/// `pointer` must be valid for reads of `size` bytes (or null for local memory), as required by
+/// `clSetKernelArg`.
+pub unsafe trait KernelArg {
+    /// Return how the argument is passed to the kernel.
+    fn kind(&self) -> ArgKind;
+
+    /// Return the size in bytes of the argument.
+    fn size(&self) -> usize;
+
+    /// Return a pointer to the argument value.
+    fn pointer(&self) -> *const c_void;
+}
+
+unsafe impl<T: Scalar> KernelArg for T {
+    fn kind(&self) -> ArgKind {
+        ArgKind::Scalar(T::type_name())
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    fn pointer(&self) -> *const c_void {
+        self as *const T as _
+    }
+}
+
+/// A kernel argument allocating local memory for `len` elements of type `T`, to be passed to a
+/// `__local` pointer parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Local<T> {
+    len: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Local<T> {
+    /// Allocate local memory for `len` elements.
+    pub fn new(len: usize) -> Self {
+        Local {
+            len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the number of elements allocated.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if no element is allocated.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+unsafe impl<T: Scalar> KernelArg for Local<T> {
+    fn kind(&self) -> ArgKind {
+        ArgKind::Local(T::type_name())
+    }
+
+    fn size(&self) -> usize {
+        self.len * mem::size_of::<T>()
+    }
+
+    fn pointer(&self) -> *const c_void {
+        ptr::null()
+    }
+}
+
+/// An error returned by `Kernel::set_arg`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SetArgError {
+    /// The argument index is not smaller than `information::NumArgs`.
+    InvalidIndex,
+
+    /// The argument value is not valid for the parameter, e.g. a memory object passed to a
+    /// scalar parameter or a memory object from another context.
+    InvalidValue,
+
+    /// The argument size does not match the size of the parameter, or is zero for local memory.
+    InvalidSize,
+}
+
+impl fmt::Display for SetArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SetArgError::InvalidIndex =>
+                write!(f, "invalid argument index"),
+            SetArgError::InvalidValue =>
+                write!(f, "invalid argument value"),
+            SetArgError::InvalidSize =>
+                write!(f, "invalid argument size"),
+        }
+    }
+}
+
+/// A single argument error found by `Kernel::validate_args`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ArgError {
+    /// No value was set for the argument.
+    Unset {
+        /// Index of the argument.
+        index: ffi::cl_uint,
+
+        /// Name of the parameter, if argument information is available.
+        name: Option<String>,
+    },
+
+    /// The value set does not match the parameter declaration.
+    Mismatch {
+        /// Index of the argument.
+        index: ffi::cl_uint,
+
+        /// Name of the parameter.
+        name: String,
+
+        /// Declaration of the parameter, e.g. `__global int*`.
+        parameter: String,
+
+        /// The value which was set.
+        argument: ArgKind,
+    },
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgError::Unset { index, name: Some(ref name) } =>
+                write!(f, "argument {} (`{}`) is not set", index, name),
+            ArgError::Unset { index, name: None } =>
+                write!(f, "argument {} is not set", index),
+            ArgError::Mismatch { index, ref name, ref parameter, argument } =>
+                write!(f, "argument {} (`{}`) is declared as `{}` but was given {}",
+                       index, name, parameter, argument),
+        }
+    }
+}
+
+/// An error returned by `Kernel::validate_args`, listing every argument error found.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ArgsError {
+    errors: Vec<ArgError>,
+}
+
+impl ArgsError {
+    /// Return the argument errors, ordered by argument index.
+    pub fn errors(&self) -> &[ArgError] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid kernel arguments:")?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// A kernel parameter declaration, as reported by `clGetKernelArgInfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Parameter {
+    pub(super) name: String,
+    pub(super) address: AddressQualifier,
+    pub(super) type_name: String,
+}
+
+impl Parameter {
+    // Render the declaration of the parameter, e.g. `__global int*`.
+    fn declaration(&self) -> String {
+        let address = match self.address {
+            AddressQualifier::Global => "__global ",
+            AddressQualifier::Constant => "__constant ",
+            AddressQualifier::Local => "__local ",
+            AddressQualifier::Private => "",
+        };
+        format!("{}{}", address, self.type_name)
+    }
+
+    // Return `true` if `argument` can be passed to this parameter.
+    fn accepts(&self, argument: ArgKind) -> bool {
+        let type_name: String = self.type_name.chars().filter(|c| !c.is_whitespace()).collect();
+        let is_pointer = type_name.ends_with('*');
+        let base = type_name.trim_end_matches('*');
+
+        let (address_matches, name) = match argument {
+            ArgKind::Scalar(name) => (self.address == AddressQualifier::Private && !is_pointer, name),
+            ArgKind::Buffer(name) => {
+                let address = self.address == AddressQualifier::Global
+                              || self.address == AddressQualifier::Constant;
+                (address && is_pointer, name)
+            }
+            ArgKind::Local(name) => (self.address == AddressQualifier::Local && is_pointer, name),
+        };
+
+        address_matches && (base == name || !BUILTIN_TYPES.contains(&base))
+    }
+}
+
+/// Check the arguments set on a kernel against its `parameters` if available. `args[i]` is the
+/// kind of the argument set at index `i`, if any.
+pub(super) fn validate(args: &[Option<ArgKind>], parameters: Option<&[Parameter]>)
+    -> Result<(), ArgsError>
+{
+    let mut errors = Vec::new();
+
+    for (index, arg) in args.iter().enumerate() {
+        let parameter = parameters.and_then(|parameters| parameters.get(index));
+        let index = index as ffi::cl_uint;
+
+        match (*arg, parameter) {
+            (None, parameter) => errors.push(ArgError::Unset {
+                index,
+                name: parameter.map(|p| p.name.clone()),
+            }),
+            (Some(argument), Some(parameter)) if !parameter.accepts(argument) => {
+                errors.push(ArgError::Mismatch {
+                    index,
+                    name: parameter.name.clone(),
+                    parameter: parameter.declaration(),
+                    argument,
+                });
+            }
+            _ => (),
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(ArgsError { errors }) }
+}
+
+#[test]
+fn test_accepts() {
+    let parameter = |address, type_name: &str| Parameter {
+        name: "p".to_owned(),
+        address,
+        type_name: type_name.to_owned(),
+    };
+
+    let global_int = parameter(AddressQualifier::Global, "int*");
+    assert!(global_int.accepts(ArgKind::Buffer("int")));
+    assert!(!global_int.accepts(ArgKind::Buffer("float")));
+    assert!(!global_int.accepts(ArgKind::Scalar("int")));
+    assert!(!global_int.accepts(ArgKind::Local("int")));
+
+    assert!(parameter(AddressQualifier::Constant, "float *").accepts(ArgKind::Buffer("float")));
+    assert!(parameter(AddressQualifier::Global, "void*").accepts(ArgKind::Buffer("uchar")));
+    assert!(parameter(AddressQualifier::Global, "float4*").accepts(ArgKind::Buffer("float")));
+    assert!(parameter(AddressQualifier::Local, "float*").accepts(ArgKind::Local("float")));
+    assert!(parameter(AddressQualifier::Private, "uint").accepts(ArgKind::Scalar("uint")));
+    assert!(!parameter(AddressQualifier::Private, "uint").accepts(ArgKind::Scalar("int")));
+    assert!(parameter(AddressQualifier::Private, "my_struct").accepts(ArgKind::Scalar("uchar")));
+}
+
+#[test]
+fn test_validate() {
+    let parameters = vec![
+        Parameter {
+            name: "data".to_owned(),
+            address: AddressQualifier::Global,
+            type_name: "int*".to_owned(),
+        },
+        Parameter {
+            name: "scale".to_owned(),
+            address: AddressQualifier::Private,
+            type_name: "float".to_owned(),
+        },
+        Parameter {
+            name: "count".to_owned(),
+            address: AddressQualifier::Private,
+            type_name: "uint".to_owned(),
+        },
+    ];
+
+    let args = [Some(ArgKind::Buffer("float")), Some(ArgKind::Scalar("float")), None];
+    let errors = validate(&args, Some(&parameters)).unwrap_err();
+    assert_eq!(
+        errors.errors(),
+        &[
+            ArgError::Mismatch {
+                index: 0,
+                name: "data".to_owned(),
+                parameter: "__global int*".to_owned(),
+                argument: ArgKind::Buffer("float"),
+            },
+            ArgError::Unset { index: 2, name: Some("count".to_owned()) },
+        ]
+    );
+
+    assert_eq!(
+        validate(&args, None).unwrap_err().errors(),
+        &[ArgError::Unset { index: 2, name: None }]
+    );
+
+    let args = [Some(ArgKind::Buffer("int")), Some(ArgKind::Scalar("float")), Some(ArgKind::Scalar("uint"))];
+    assert_eq!(validate(&args, Some(&parameters)), Ok(()));
+}
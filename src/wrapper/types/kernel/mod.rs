@@ -0,0 +1,574 @@
+//! A module defining the `cl_kernel` related types, such as the high-level `Kernel` type.
+
+use wrapper::ffi;
+use wrapper::types::device::{self, Device};
+use wrapper::information::InformationResult;
+use errors::*;
+use std::fmt;
+
+pub mod arg;
+pub mod typed;
+pub mod pool;
+pub mod tuner;
+pub mod report;
+
+use self::arg::{KernelArg, ArgKind, SetArgError, ArgsError, Parameter};
+
+enumz!(
+    AddressQualifier,
+    ffi::cl_kernel_arg_address_qualifier,
+    "cl_kernel_arg_address_qualifier",
+    Global => [ffi::CL_KERNEL_ARG_ADDRESS_GLOBAL, "CL_KERNEL_ARG_ADDRESS_GLOBAL"],
+    Local => [ffi::CL_KERNEL_ARG_ADDRESS_LOCAL, "CL_KERNEL_ARG_ADDRESS_LOCAL"],
+    Constant => [ffi::CL_KERNEL_ARG_ADDRESS_CONSTANT, "CL_KERNEL_ARG_ADDRESS_CONSTANT"],
+    Private => [ffi::CL_KERNEL_ARG_ADDRESS_PRIVATE, "CL_KERNEL_ARG_ADDRESS_PRIVATE"]
+);
+
+enumz!(
+    AccessQualifier,
+    ffi::cl_kernel_arg_access_qualifier,
+    "cl_kernel_arg_access_qualifier",
+    ReadOnly => [ffi::CL_KERNEL_ARG_ACCESS_READ_ONLY, "CL_KERNEL_ARG_ACCESS_READ_ONLY"],
+    WriteOnly => [ffi::CL_KERNEL_ARG_ACCESS_WRITE_ONLY, "CL_KERNEL_ARG_ACCESS_WRITE_ONLY"],
+    ReadWrite => [ffi::CL_KERNEL_ARG_ACCESS_READ_WRITE, "CL_KERNEL_ARG_ACCESS_READ_WRITE"],
+    None => [ffi::CL_KERNEL_ARG_ACCESS_NONE, "CL_KERNEL_ARG_ACCESS_NONE"]
+);
+
+bitfield!(
+    TypeQualifier,
+    "cl_kernel_arg_type_qualifier",
+    [is_const, "const"] => ffi::CL_KERNEL_ARG_TYPE_CONST,
+    [is_restrict, "restrict"] => ffi::CL_KERNEL_ARG_TYPE_RESTRICT,
+    [is_volatile, "volatile"] => ffi::CL_KERNEL_ARG_TYPE_VOLATILE
+);
+
+pub mod information {
+    //! A module containing the information marker types for `Kernel`.
+
+    use wrapper::ffi;
+    use wrapper::information::*;
+    use wrapper::types::context;
+    use wrapper::types::program;
+
+    /// A trait implemented by marker types for retrieving information through `clGetKernelInfo`.
+    pub trait KernelInformation: Information<ffi::cl_kernel_info> { }
+
+    // Build a kernel from a program with kernel argument information enabled, and call
+    // `$info_fun::<$type>` on it with the additional arguments `$args`.
+    macro_rules! test_fun {
+        ($test_fun: ident, $type: ident, $info_fun: ident $(, $args: expr)*) => {
+            #[test]
+            fn $test_fun() {
+                use futures::Future;
+
+                let context = context::Context::default().unwrap();
+                let program = program::Builder::create_with_sources(
+                    Some("__kernel void addFFT(__global float * filter, __global float * temp, float coeff) {
+                        int ind = get_global_id(0);
+                        filter[2 * ind] += temp[ind] * coeff;
+                    }"),
+                    &context
+                ).unwrap();
+                let program = program.build_with_options("-cl-kernel-arg-info").wait().unwrap();
+                let kernel = program.create_kernel("addFFT").unwrap();
+                let _ = kernel.$info_fun::<$type>($($args),*);
+            }
+        };
+    }
+
+    macro_rules! info_impl {
+        ($type: ident, $result: ty, $id: expr, $id_name: expr, $test_fun: ident) => {
+            general_info_impl!(KernelInformation, ffi::cl_kernel_info, $type, $result, $id, $id_name);
+
+            test_fun!($test_fun, $type, get_info);
+        };
+    }
+
+    info_impl!(FunctionName, String, ffi::CL_KERNEL_FUNCTION_NAME, "CL_KERNEL_FUNCTION_NAME", test_function_name);
+    info_impl!(NumArgs, ffi::cl_uint, ffi::CL_KERNEL_NUM_ARGS, "CL_KERNEL_NUM_ARGS", test_num_args);
+    info_impl!(ReferenceCount, ffi::cl_uint, ffi::CL_KERNEL_REFERENCE_COUNT, "CL_KERNEL_REFERENCE_COUNT", test_reference_count);
+    info_impl!(Context, context::Context, ffi::CL_KERNEL_CONTEXT, "CL_KERNEL_CONTEXT", test_context);
+    info_impl!(Program, program::Program, ffi::CL_KERNEL_PROGRAM, "CL_KERNEL_PROGRAM", test_program);
+    info_impl!(Attributes, String, ffi::CL_KERNEL_ATTRIBUTES, "CL_KERNEL_ATTRIBUTES", test_attributes);
+
+    /// A trait implemented by marker types for retrieving information through
+    /// `clGetKernelArgInfo`.
+    pub trait KernelArgInformation: Information<ffi::cl_kernel_arg_info> { }
+
+    macro_rules! arg_info_impl {
+        ($type: ident, $result: ty, $id: expr, $id_name: expr, $test_fun: ident) => {
+            general_info_impl!(KernelArgInformation, ffi::cl_kernel_arg_info, $type, $result, $id, $id_name);
+
+            test_fun!($test_fun, $type, get_arg_info, 0);
+        };
+    }
+
+    arg_info_impl!(AddressQualifier, super::AddressQualifier, ffi::CL_KERNEL_ARG_ADDRESS_QUALIFIER, "CL_KERNEL_ARG_ADDRESS_QUALIFIER", test_address_qualifier);
+    arg_info_impl!(AccessQualifier, super::AccessQualifier, ffi::CL_KERNEL_ARG_ACCESS_QUALIFIER, "CL_KERNEL_ARG_ACCESS_QUALIFIER", test_access_qualifier);
+    arg_info_impl!(TypeName, String, ffi::CL_KERNEL_ARG_TYPE_NAME, "CL_KERNEL_ARG_TYPE_NAME", test_type_name);
+    arg_info_impl!(TypeQualifier, super::TypeQualifier, ffi::CL_KERNEL_ARG_TYPE_QUALIFIER, "CL_KERNEL_ARG_TYPE_QUALIFIER", test_type_qualifier);
+    arg_info_impl!(ArgName, String, ffi::CL_KERNEL_ARG_NAME, "CL_KERNEL_ARG_NAME", test_arg_name);
+
+    /// A trait implemented by marker types for retrieving information through
+    /// `clGetKernelWorkGroupInfo`.
+    pub trait KernelWorkGroupInformation: Information<ffi::cl_kernel_work_group_info> { }
+
+    macro_rules! work_group_info_impl {
+        ($type: ident, $result: ty, $id: expr, $id_name: expr, $test_fun: ident) => {
+            general_info_impl!(KernelWorkGroupInformation, ffi::cl_kernel_work_group_info, $type, $result, $id, $id_name);
+
+            test_fun!($test_fun, $type, get_work_group_info, &::wrapper::types::device::Device::default().unwrap());
+        };
+    }
+
+    work_group_info_impl!(WorkGroupSize, usize, ffi::CL_KERNEL_WORK_GROUP_SIZE, "CL_KERNEL_WORK_GROUP_SIZE", test_work_group_size);
+    work_group_info_impl!(CompileWorkGroupSize, Vec<usize>, ffi::CL_KERNEL_COMPILE_WORK_GROUP_SIZE, "CL_KERNEL_COMPILE_WORK_GROUP_SIZE", test_compile_work_group_size);
+    work_group_info_impl!(LocalMemSize, ffi::cl_ulong, ffi::CL_KERNEL_LOCAL_MEM_SIZE, "CL_KERNEL_LOCAL_MEM_SIZE", test_local_mem_size);
+    work_group_info_impl!(PreferredWorkGroupSizeMultiple, usize, ffi::CL_KERNEL_PREFERRED_WORK_GROUP_SIZE_MULTIPLE, "CL_KERNEL_PREFERRED_WORK_GROUP_SIZE_MULTIPLE", test_preferred_work_group_size_multiple);
+    work_group_info_impl!(PrivateMemSize, ffi::cl_ulong, ffi::CL_KERNEL_PRIVATE_MEM_SIZE, "CL_KERNEL_PRIVATE_MEM_SIZE", test_private_mem_size);
+}
+
+/// `Kernel` is a high-level type which maps to the low-level `cl_kernel` OpenCL type.
+/// An object of type `Kernel` uniquely owns an OpenCL kernel.
+///
+/// Since kernel arguments are part of the state of a `cl_kernel`, `Kernel` does not implement
+/// `Clone`: arguments can only be set through a `&mut Kernel`, so that two threads cannot race
+/// on the arguments of the same kernel. Use `Kernel::duplicate` or a `pool::KernelPool` to
+/// launch the same kernel function from several threads.
+///
+/// A `Kernel` also keeps track of the arguments set through `Kernel::set_arg`, so that they can
+/// be validated before launch (see `Kernel::enable_validation`).
+pub struct Kernel {
+    kernel: ffi::cl_kernel,
+    args: Vec<Option<ArgKind>>,
+    parameters: Option<Vec<Parameter>>,
+}
+
+unsafe impl Send for Kernel { }
+unsafe impl Sync for Kernel { }
+
+/// An error returned by `Program::create_kernel` and `Program::create_kernels`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum CreationError {
+    /// No kernel with the requested name was found in the program.
+    InvalidKernelName,
+
+    /// The program has not been successfully built for any of its devices.
+    InvalidProgramExecutable,
+
+    /// The kernel function definition (e.g. the number or types of arguments) differs between
+    /// the devices the program was built for.
+    InvalidKernelDefinition,
+}
+
+/// An error returned by `Kernel::get_arg_info`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ArgInfoError {
+    /// Argument information is not available, i.e. the program was not built with the
+    /// `-cl-kernel-arg-info` option.
+    NotAvailable,
+
+    /// The argument index is not smaller than `information::NumArgs`.
+    InvalidIndex,
+}
+
+impl fmt::Display for ArgInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgInfoError::NotAvailable =>
+                write!(f, "argument information not available (see `-cl-kernel-arg-info`)"),
+            ArgInfoError::InvalidIndex =>
+                write!(f, "invalid argument index"),
+        }
+    }
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::InvalidKernelName =>
+                write!(f, "no kernel with this name in the program"),
+            CreationError::InvalidProgramExecutable =>
+                write!(f, "the program has not been successfully built"),
+            CreationError::InvalidKernelDefinition =>
+                write!(f, "the kernel definition differs between devices"),
+        }
+    }
+}
+
+impl Kernel {
+    pub(super) unsafe fn from_ffi(kernel: ffi::cl_kernel, retain: bool) -> Self {
+        if retain {
+            catch_ffi(ffi::clRetainKernel(kernel)).unwrap();
+        }
+
+        let num_args = InformationResult::get_info(|size, value, ret_size| {
+            ffi::clGetKernelInfo(kernel, ffi::CL_KERNEL_NUM_ARGS, size, value as _, ret_size)
+        });
+        let num_args: ffi::cl_uint = expect!(
+            num_args,
+            ffi::CL_OUT_OF_RESOURCES,
+            ffi::CL_OUT_OF_HOST_MEMORY
+        );
+
+        Kernel {
+            kernel,
+            args: vec![None; num_args as usize],
+            parameters: None,
+        }
+    }
+
+    pub(super) unsafe fn underlying(&self) -> ffi::cl_kernel {
+        self.kernel
+    }
+
+    /// Create a new, independent kernel for the same kernel function, from the program owning
+    /// `self`. The arguments of the new kernel are not set, but validation is enabled on the new
+    /// kernel if it was enabled on `self`.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn duplicate(&self) -> Kernel {
+        let program = self.get_info::<information::Program>();
+        let name = self.get_info::<information::FunctionName>();
+
+        // The program was successfully built since `self` exists.
+        let mut kernel = program.create_kernel(&name)
+                                .expect("the program of an existing kernel should be built");
+        kernel.parameters = self.parameters.clone();
+        kernel
+    }
+
+    /// Query an information to the kernel. `T` should be a marker type from the `information`
+    /// module implementing `KernelInformation`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{Context, program, kernel, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void my_kernel(__global float * buffer) { }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// let program = program.build().wait().map_err(|_| "build failed")?;
+    /// let kernel = program.create_kernel("my_kernel").map_err(|_| "no such kernel")?;
+    /// assert_eq!(kernel.get_info::<kernel::information::NumArgs>(), 1);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources, or if an invalid information
+    /// param is passed (should only happen when a user incorrectly implements
+    /// `KernelInformation` on their own or if the information is not supported on the kernel
+    /// and cargo features have not been set correctly, otherwise it is a bug).
+    pub fn get_info<T: information::KernelInformation>(&self) -> T::Result {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetKernelInfo(
+                    self.kernel,
+                    T::id(),
+                    size,
+                    value as _,
+                    ret_size
+                )
+            })
+        };
+
+        expect!(
+            result,
+            ffi::CL_OUT_OF_RESOURCES,
+            ffi::CL_OUT_OF_HOST_MEMORY,
+            ffi::CL_INVALID_VALUE
+        )
+    }
+
+    /// Query an information about the argument at index `index`. `T` should be a marker type
+    /// from the `information` module implementing `KernelArgInformation`.
+    ///
+    /// # Errors
+    /// * `ArgInfoError::NotAvailable` if the program was not built with `-cl-kernel-arg-info`.
+    /// * `ArgInfoError::InvalidIndex` if `index` is not a valid argument index.
+    ///
+    /// # Panics
+    /// Same as `get_info`.
+    pub fn get_arg_info<T: information::KernelArgInformation>(&self, index: ffi::cl_uint)
+        -> Result<T::Result, ArgInfoError>
+    {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetKernelArgInfo(
+                    self.kernel,
+                    index,
+                    T::id(),
+                    size,
+                    value as _,
+                    ret_size
+                )
+            })
+        };
+
+        match result {
+            Err(RawError(ffi::CL_KERNEL_ARG_INFO_NOT_AVAILABLE)) => Err(ArgInfoError::NotAvailable),
+            Err(RawError(ffi::CL_INVALID_ARG_INDEX)) => Err(ArgInfoError::InvalidIndex),
+            result => Ok(expect!(
+                result,
+                ffi::CL_OUT_OF_RESOURCES,
+                ffi::CL_OUT_OF_HOST_MEMORY,
+                ffi::CL_INVALID_VALUE
+            )),
+        }
+    }
+
+    /// Query a work-group information of the kernel specific to `device`. `T` should be a marker
+    /// type from the `information` module implementing `KernelWorkGroupInformation`.
+    ///
+    /// # Panics
+    /// Same as `get_info`, or if `device` is not one of the devices the program of the kernel
+    /// was built for.
+    pub fn get_work_group_info<T: information::KernelWorkGroupInformation>(&self, device: &Device)
+        -> T::Result
+    {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetKernelWorkGroupInfo(
+                    self.kernel,
+                    device.underlying(),
+                    T::id(),
+                    size,
+                    value as _,
+                    ret_size
+                )
+            })
+        };
+
+        expect!(
+            result,
+            ffi::CL_OUT_OF_RESOURCES,
+            ffi::CL_OUT_OF_HOST_MEMORY,
+            ffi::CL_INVALID_VALUE,
+            ffi::CL_INVALID_DEVICE
+        )
+    }
+
+    /// Estimate the resource usage of launching the kernel with `local_size` on `device`, with
+    /// the arguments currently set (local memory arguments are accounted for), and flag the
+    /// launches which will fail because of the device limits.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{Context, Device, program, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let device = Device::default().ok_or("no default device")?;
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void my_kernel(__global float * buffer) {
+    ///         __local float tile[256];
+    ///         size_t local_id = get_local_id(0);
+    ///         tile[local_id] = buffer[get_global_id(0)];
+    ///         barrier(CLK_LOCAL_MEM_FENCE);
+    ///         buffer[get_global_id(0)] = tile[255 - local_id];
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// let program = program.build().wait().map_err(|_| "build failed")?;
+    /// let kernel = program.create_kernel("my_kernel").map_err(|_| "no such kernel")?;
+    ///
+    /// let report = kernel.resource_report(&device, &[256]);
+    /// assert!(report.local_mem_per_work_group() >= 1024);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Panics
+    /// Same as `get_work_group_info`.
+    pub fn resource_report(&self, device: &Device, local_size: &[usize]) -> report::ResourceReport {
+        let limits = report::Limits {
+            device_local_mem: device.get_info::<device::information::LocalMemSize>(),
+            compute_units: device.get_info::<device::information::MaxComputeUnits>(),
+            device_max_work_group_size: device.get_info::<device::information::MaxWorkGroupSize>(),
+            kernel_max_work_group_size: self.get_work_group_info::<information::WorkGroupSize>(device),
+            preferred_multiple:
+                self.get_work_group_info::<information::PreferredWorkGroupSizeMultiple>(device),
+            local_mem: self.get_work_group_info::<information::LocalMemSize>(device),
+            private_mem: self.get_work_group_info::<information::PrivateMemSize>(device),
+        };
+
+        report::ResourceReport::new(local_size, limits)
+    }
+
+    /// Set the argument at index `index` to `arg`. The value is copied by the driver, so `arg`
+    /// can be dropped afterwards. However, the driver is not required to retain memory
+    /// objects: a buffer set as an argument must outlive the commands enqueued with the kernel,
+    /// and the argument must be set again once the buffer is dropped.
+    ///
+    /// # Errors
+    /// * `SetArgError::InvalidIndex` if `index` is not a valid argument index.
+    /// * `SetArgError::InvalidValue` if `arg` is not a valid value for this parameter.
+    /// * `SetArgError::InvalidSize` if the size of `arg` does not match the parameter.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn set_arg<A: KernelArg + ?Sized>(&mut self, index: ffi::cl_uint, arg: &A)
+        -> Result<(), SetArgError>
+    {
+        let error = unsafe {
+            ffi::clSetKernelArg(self.kernel, index, arg.size(), arg.pointer())
+        };
+
+        match error {
+            ffi::CL_INVALID_ARG_INDEX => return Err(SetArgError::InvalidIndex),
+            ffi::CL_INVALID_ARG_VALUE | ffi::CL_INVALID_MEM_OBJECT | ffi::CL_INVALID_SAMPLER =>
+                return Err(SetArgError::InvalidValue),
+            ffi::CL_INVALID_ARG_SIZE => return Err(SetArgError::InvalidSize),
+            _ => (),
+        }
+
+        expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+        self.args[index as usize] = Some(arg.kind());
+        Ok(())
+    }
+
+    // Mark the argument at index `index` as unset, e.g. when the memory object it was set to
+    // is about to be released, so that `validate_args` reports it.
+    pub(super) fn clear_arg(&mut self, index: ffi::cl_uint) {
+        if let Some(arg) = self.args.get_mut(index as usize) {
+            *arg = None;
+        }
+    }
+
+    /// Enable validation of the arguments against the parameter declarations reported by the
+    /// driver: `CommandQueue::enqueue_nd_range` will then call `Kernel::validate_args` before
+    /// enqueuing the kernel.
+    ///
+    /// # Errors
+    /// `ArgInfoError::NotAvailable` if the program was not built with `-cl-kernel-arg-info`, in
+    /// which case validation stays disabled.
+    ///
+    /// # Panics
+    /// Same as `get_arg_info`.
+    pub fn enable_validation(&mut self) -> Result<(), ArgInfoError> {
+        let mut parameters = Vec::with_capacity(self.args.len());
+
+        for index in 0..self.args.len() as ffi::cl_uint {
+            parameters.push(Parameter {
+                name: self.get_arg_info::<information::ArgName>(index)?,
+                address: self.get_arg_info::<information::AddressQualifier>(index)?,
+                type_name: self.get_arg_info::<information::TypeName>(index)?,
+            });
+        }
+
+        self.parameters = Some(parameters);
+        Ok(())
+    }
+
+    /// Disable validation of the arguments.
+    pub fn disable_validation(&mut self) {
+        self.parameters = None;
+    }
+
+    /// Return `true` if validation of the arguments is enabled.
+    pub fn validation_enabled(&self) -> bool {
+        self.parameters.is_some()
+    }
+
+    /// Check that every argument has been set and, if validation is enabled, that it matches
+    /// the parameter declaration: buffers must be passed to `__global` or `__constant`
+    /// pointers, local memory to `__local` pointers and scalars by value, with the same
+    /// (element) type when the parameter is declared with a builtin scalar type.
+    ///
+    /// # Errors
+    /// `ArgsError` listing every unset or mismatched argument.
+    pub fn validate_args(&self) -> Result<(), ArgsError> {
+        arg::validate(&self.args, self.parameters.as_deref())
+    }
+}
+
+impl PartialEq for Kernel {
+    fn eq(&self, other: &Self) -> bool {
+        self.kernel == other.kernel
+    }
+}
+
+impl Eq for Kernel { }
+
+impl Drop for Kernel {
+    fn drop(&mut self) {
+        catch_ffi(unsafe { ffi::clReleaseKernel(self.kernel) }).unwrap();
+    }
+}
+
+impl fmt::Debug for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Kernel")
+         .field("function_name", &self.get_info::<information::FunctionName>())
+         .finish()
+    }
+}
+
+#[test]
+fn test_validation_before_launch() {
+    use wrapper::types::{program, mem, command_queue};
+    use futures::Future;
+
+    let queue = command_queue::CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void scale(__global int * data, float factor, uint count) {
+            if (get_global_id(0) < count) {
+                data[get_global_id(0)] *= factor;
+            }
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build_with_options("-cl-kernel-arg-info").wait().unwrap();
+    let mut kernel = program.create_kernel("scale").unwrap();
+    kernel.enable_validation().unwrap();
+
+    let floats = mem::Buffer::create(vec![1.0f32; 4], &context, mem::Flags::new()).unwrap();
+    kernel.set_arg(0, &floats).unwrap();
+    kernel.set_arg(1, &2.0f32).unwrap();
+
+    let range = command_queue::NdRange::new(&[4]);
+    match queue.enqueue_nd_range(&kernel, &range) {
+        Err(command_queue::LaunchError::InvalidArgs(err)) => assert_eq!(err.errors().len(), 2),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let ints = mem::Buffer::create(vec![1i32; 4], &context, mem::Flags::new()).unwrap();
+    kernel.set_arg(0, &ints).unwrap();
+    kernel.set_arg(2, &4u32).unwrap();
+    queue.enqueue_nd_range(&kernel, &range).unwrap().wait().unwrap();
+}
+
+#[test]
+fn test_duplicate() {
+    use wrapper::types::{context, program};
+    use futures::Future;
+
+    let context = context::Context::default().unwrap();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void fill(__global int * data, int value) {
+            data[get_global_id(0)] = value;
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build_with_options("-cl-kernel-arg-info").wait().unwrap();
+    let mut kernel = program.create_kernel("fill").unwrap();
+    kernel.enable_validation().unwrap();
+    kernel.set_arg(1, &3i32).unwrap();
+
+    let duplicate = kernel.duplicate();
+    assert!(duplicate != kernel);
+    assert!(duplicate.validation_enabled());
+    assert_eq!(duplicate.get_info::<information::FunctionName>(), "fill");
+    assert_eq!(duplicate.validate_args().unwrap_err().errors().len(), 2);
+    assert_eq!(kernel.validate_args().unwrap_err().errors().len(), 1);
+}
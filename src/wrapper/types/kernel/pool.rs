@@ -0,0 +1,180 @@
+//! A module defining `KernelPool`, for launching the same kernel function from several threads.
+
+use super::Kernel;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+use std::fmt;
+
+/// A pool of independent kernels for the same kernel function. Each thread checks out its own
+/// kernel through `KernelPool::get`, sets its arguments and launches it without racing with the
+/// other threads. Kernels are created on demand through `Kernel::duplicate` and go back to the
+/// pool when the `PooledKernel` handle is dropped.
+///
+/// Note that a kernel taken from the pool keeps the arguments set by its previous user: all
+/// the arguments should be set again before launching it.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{Context, program, Future};
+/// use gprust::kernel::pool::KernelPool;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let context = Context::default().ok_or("no default context")?;
+/// let program = program::Builder::create_with_sources(
+///     Some("__kernel void fill(__global int * data, int value) { }"),
+///     &context
+/// ).expect("I did provide a source");
+/// let program = program.build().wait().map_err(|_| "build failed")?;
+/// let kernel = program.create_kernel("fill").map_err(|_| "no such kernel")?;
+///
+/// let pool = Arc::new(KernelPool::new(kernel));
+/// let handles: Vec<_> = (0..4i32).map(|i| {
+///     let pool = pool.clone();
+///     thread::spawn(move || {
+///         let mut kernel = pool.get();
+///         kernel.set_arg(1, &i).unwrap();
+///         /* set the other arguments and launch the kernel */
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     handle.join().map_err(|_| "thread panicked")?;
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+pub struct KernelPool {
+    prototype: Kernel,
+    idle: Mutex<Vec<Kernel>>,
+}
+
+impl KernelPool {
+    /// Create a pool duplicating `kernel`. `kernel` itself is only used as a prototype and is
+    /// never handed out, so validation should be enabled on it beforehand if needed.
+    pub fn new(kernel: Kernel) -> Self {
+        KernelPool {
+            prototype: kernel,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Check out a kernel, reusing an idle one if any.
+    ///
+    /// # Panics
+    /// Same as `Kernel::duplicate`.
+    pub fn get(&self) -> PooledKernel<'_> {
+        let kernel = self.idle.lock().unwrap().pop();
+
+        PooledKernel {
+            kernel: Some(kernel.unwrap_or_else(|| self.prototype.duplicate())),
+            pool: self,
+        }
+    }
+
+    /// Return the number of idle kernels in the pool.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Return the prototype kernel, e.g. for querying information.
+    pub fn prototype(&self) -> &Kernel {
+        &self.prototype
+    }
+}
+
+impl fmt::Debug for KernelPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KernelPool")
+         .field("prototype", &self.prototype)
+         .field("idle", &self.idle())
+         .finish()
+    }
+}
+
+/// A kernel checked out from a `KernelPool`, giving exclusive access to it. The kernel goes
+/// back to the pool on drop.
+pub struct PooledKernel<'a> {
+    kernel: Option<Kernel>,
+    pool: &'a KernelPool,
+}
+
+impl<'a> Deref for PooledKernel<'a> {
+    type Target = Kernel;
+
+    fn deref(&self) -> &Kernel {
+        self.kernel.as_ref().unwrap()
+    }
+}
+
+impl<'a> DerefMut for PooledKernel<'a> {
+    fn deref_mut(&mut self) -> &mut Kernel {
+        self.kernel.as_mut().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledKernel<'a> {
+    fn drop(&mut self) {
+        if let Some(kernel) = self.kernel.take() {
+            // Do not panic while unwinding if another thread poisoned the lock.
+            if let Ok(mut idle) = self.pool.idle.lock() {
+                idle.push(kernel);
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Debug for PooledKernel<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PooledKernel")
+         .field(&**self)
+         .finish()
+    }
+}
+
+#[test]
+fn test_pool() {
+    use wrapper::types::{program, mem, command_queue};
+    use futures::Future;
+    use std::sync::Arc;
+    use std::thread;
+
+    let queue = command_queue::CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void fill(__global int * data, int value) {
+            data[get_global_id(0)] = value;
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+    let pool = Arc::new(KernelPool::new(program.create_kernel("fill").unwrap()));
+
+    let handles: Vec<_> = (0..4i32).map(|i| {
+        let (pool, queue, context) = (pool.clone(), queue.clone(), context.clone());
+        thread::spawn(move || {
+            let data = mem::Buffer::create(vec![0i32; 64], &context, mem::Flags::new()).unwrap();
+            let mut kernel = pool.get();
+            kernel.set_arg(0, &data).unwrap();
+            kernel.set_arg(1, &i).unwrap();
+            queue.enqueue_nd_range(&kernel, &command_queue::NdRange::new(&[64]))
+                 .unwrap()
+                 .wait_for_completion()
+                 .unwrap();
+        })
+    }).collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let idle = pool.idle();
+    assert!((1..=4).contains(&idle));
+    let first = pool.get();
+    assert_eq!(pool.idle(), idle - 1);
+    drop(first);
+    assert_eq!(pool.idle(), idle);
+}
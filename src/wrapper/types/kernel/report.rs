@@ -0,0 +1,216 @@
+//! A module defining `ResourceReport`, an estimate of the resource usage and occupancy of a
+//! kernel launch on a device.
+
+use wrapper::ffi;
+use std::{cmp, fmt};
+
+/// A problem found in a `ResourceReport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Issue {
+    /// A work-group requests more local memory than available on the device: the launch will
+    /// fail.
+    LocalMemoryExceeded {
+        /// Local memory used by a work-group, in bytes.
+        requested: ffi::cl_ulong,
+
+        /// Local memory available on the device, in bytes.
+        available: ffi::cl_ulong,
+    },
+
+    /// The work-group size exceeds the maximum supported by the device for this kernel: the
+    /// launch will fail.
+    WorkGroupTooLarge {
+        /// Requested work-group size.
+        requested: usize,
+
+        /// Maximum work-group size for this kernel on the device.
+        maximum: usize,
+    },
+
+    /// The work-group size is not a multiple of the preferred work-group size multiple, so that
+    /// some hardware threads are left idle.
+    NotPreferredMultiple {
+        /// Requested work-group size.
+        requested: usize,
+
+        /// The preferred work-group size multiple.
+        multiple: usize,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Issue::LocalMemoryExceeded { requested, available } =>
+                write!(f, "a work-group uses {} bytes of local memory but only {} are available",
+                       requested, available),
+            Issue::WorkGroupTooLarge { requested, maximum } =>
+                write!(f, "work-group size {} exceeds the maximum of {}", requested, maximum),
+            Issue::NotPreferredMultiple { requested, multiple } =>
+                write!(f, "work-group size {} is not a multiple of {}", requested, multiple),
+        }
+    }
+}
+
+// The device and kernel limits a report is computed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Limits {
+    pub(super) device_local_mem: ffi::cl_ulong,
+    pub(super) compute_units: ffi::cl_uint,
+    pub(super) device_max_work_group_size: usize,
+    pub(super) kernel_max_work_group_size: usize,
+    pub(super) preferred_multiple: usize,
+    pub(super) local_mem: ffi::cl_ulong,
+    pub(super) private_mem: ffi::cl_ulong,
+}
+
+/// An estimate of the resource usage of a kernel launched with a given local size on a device,
+/// as returned by `Kernel::resource_report`.
+///
+/// The occupancy estimate assumes that a compute unit can hold as many work-items as
+/// `device::information::MaxWorkGroupSize`, and that local memory is the only other limiting
+/// resource: it is meant for spotting regressions rather than for exact figures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceReport {
+    work_group_size: usize,
+    limits: Limits,
+    issues: Vec<Issue>,
+}
+
+impl ResourceReport {
+    pub(super) fn new(local_size: &[usize], limits: Limits) -> Self {
+        let work_group_size = local_size.iter().product();
+        let mut issues = Vec::new();
+
+        if limits.local_mem > limits.device_local_mem {
+            issues.push(Issue::LocalMemoryExceeded {
+                requested: limits.local_mem,
+                available: limits.device_local_mem,
+            });
+        }
+
+        let maximum = cmp::min(limits.device_max_work_group_size, limits.kernel_max_work_group_size);
+        if work_group_size > maximum {
+            issues.push(Issue::WorkGroupTooLarge { requested: work_group_size, maximum });
+        }
+
+        if limits.preferred_multiple > 1 && work_group_size % limits.preferred_multiple != 0 {
+            issues.push(Issue::NotPreferredMultiple {
+                requested: work_group_size,
+                multiple: limits.preferred_multiple,
+            });
+        }
+
+        ResourceReport {
+            work_group_size,
+            limits,
+            issues,
+        }
+    }
+
+    /// Return the work-group size, i.e. the product of the local size.
+    pub fn work_group_size(&self) -> usize {
+        self.work_group_size
+    }
+
+    /// Return the local memory used by a work-group in bytes, including the local memory
+    /// arguments set on the kernel.
+    pub fn local_mem_per_work_group(&self) -> ffi::cl_ulong {
+        self.limits.local_mem
+    }
+
+    /// Return the local memory available on the device in bytes.
+    pub fn device_local_mem(&self) -> ffi::cl_ulong {
+        self.limits.device_local_mem
+    }
+
+    /// Return the private memory used by a work-item in bytes.
+    pub fn private_mem_per_work_item(&self) -> ffi::cl_ulong {
+        self.limits.private_mem
+    }
+
+    /// Return the private memory used by a work-group in bytes.
+    pub fn private_mem_per_work_group(&self) -> ffi::cl_ulong {
+        self.limits.private_mem * self.work_group_size as ffi::cl_ulong
+    }
+
+    /// Return the number of compute units of the device.
+    pub fn compute_units(&self) -> ffi::cl_uint {
+        self.limits.compute_units
+    }
+
+    /// Return the estimated number of work-groups which can be resident on a compute unit at
+    /// the same time, zero meaning that the launch will fail.
+    pub fn work_groups_per_compute_unit(&self) -> usize {
+        if self.issues.iter().any(|issue| !matches!(*issue, Issue::NotPreferredMultiple { .. })) {
+            return 0;
+        }
+
+        let by_work_items = self.limits.device_max_work_group_size / cmp::max(self.work_group_size, 1);
+        match self.limits.device_local_mem.checked_div(self.limits.local_mem) {
+            Some(by_local_mem) => cmp::min(by_work_items as ffi::cl_ulong, by_local_mem) as usize,
+            None => by_work_items,
+        }
+    }
+
+    /// Return the estimated number of work-groups resident on the whole device at the same time.
+    pub fn resident_work_groups(&self) -> usize {
+        self.work_groups_per_compute_unit() * self.limits.compute_units as usize
+    }
+
+    /// Return the problems found, an empty slice meaning that the launch should succeed.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "work-group size: {}", self.work_group_size)?;
+        writeln!(f, "local memory per work-group: {} / {} bytes",
+                 self.limits.local_mem, self.limits.device_local_mem)?;
+        writeln!(f, "private memory per work-item: {} bytes", self.limits.private_mem)?;
+        write!(f, "work-groups per compute unit: {} ({} compute units)",
+               self.work_groups_per_compute_unit(), self.limits.compute_units)?;
+
+        for issue in &self.issues {
+            write!(f, "\nwarning: {}", issue)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_report() {
+    let limits = Limits {
+        device_local_mem: 32 * 1024,
+        compute_units: 8,
+        device_max_work_group_size: 1024,
+        kernel_max_work_group_size: 512,
+        preferred_multiple: 32,
+        local_mem: 4 * 1024,
+        private_mem: 16,
+    };
+
+    let report = ResourceReport::new(&[16, 16], limits);
+    assert_eq!(report.work_group_size(), 256);
+    assert_eq!(report.work_groups_per_compute_unit(), 4);
+    assert_eq!(report.resident_work_groups(), 32);
+    assert_eq!(report.private_mem_per_work_group(), 4096);
+    assert!(report.issues().is_empty());
+
+    let report = ResourceReport::new(&[8], Limits { local_mem: 0, ..limits });
+    assert_eq!(report.work_groups_per_compute_unit(), 128);
+    assert_eq!(report.issues(), &[Issue::NotPreferredMultiple { requested: 8, multiple: 32 }]);
+
+    let report = ResourceReport::new(&[1024], Limits { local_mem: 64 * 1024, ..limits });
+    assert_eq!(report.work_groups_per_compute_unit(), 0);
+    assert_eq!(
+        report.issues(),
+        &[
+            Issue::LocalMemoryExceeded { requested: 64 * 1024, available: 32 * 1024 },
+            Issue::WorkGroupTooLarge { requested: 1024, maximum: 512 },
+        ]
+    );
+}
@@ -0,0 +1,379 @@
+//! A module defining `Tuner`, an auto-tuner for the local size of kernels whose results are
+//! persisted per device.
+
+use wrapper::types::command_queue::{self, CommandQueue, NdRange, LaunchError};
+use wrapper::types::device::{self, Device};
+use wrapper::types::event::ExecutionError;
+use wrapper::ffi;
+use super::{Kernel, information};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::{cmp, fmt, fs};
+
+// Tuning results are only valid for a given kernel on a given device and driver.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Key {
+    device: String,
+    driver: String,
+    kernel: String,
+}
+
+impl Key {
+    fn new(device: &Device, kernel: &Kernel) -> Self {
+        Key {
+            device: device.get_info::<device::information::Name>(),
+            driver: device.get_info::<device::information::DriverVersion>(),
+            kernel: kernel.get_info::<information::FunctionName>(),
+        }
+    }
+}
+
+/// An error returned by `Tuner::tune`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TuneError {
+    /// The command queue was not created with the `profiling` property.
+    ProfilingNotEnabled,
+
+    /// The global size is empty, has more than 3 dimensions or a zero dimension.
+    InvalidGlobalSize,
+
+    /// No candidate local size could be launched.
+    NoCandidate,
+
+    /// Launching a candidate failed for another reason than its local size, e.g. because some
+    /// arguments are not set.
+    Launch(LaunchError),
+
+    /// A candidate launch was abnormally terminated.
+    Execution(ExecutionError),
+}
+
+impl fmt::Display for TuneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TuneError::ProfilingNotEnabled =>
+                write!(f, "profiling is not enabled on the command queue"),
+            TuneError::InvalidGlobalSize =>
+                write!(f, "invalid global size"),
+            TuneError::NoCandidate =>
+                write!(f, "no candidate local size could be launched"),
+            TuneError::Launch(ref err) =>
+                write!(f, "{}", err),
+            TuneError::Execution(err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+/// Return the candidate local sizes for launching `kernel` on `device` over `global`: in each
+/// dimension, the divisors of the global size which are either powers of two or (in the first
+/// dimension) multiples of `kernel::information::PreferredWorkGroupSizeMultiple`, within the
+/// limits given by `device::information::MaxWorkItemSizes`, `MaxWorkGroupSize` and
+/// `kernel::information::WorkGroupSize`.
+///
+/// # Panics
+/// Same as `Kernel::get_work_group_info`.
+pub fn candidates(kernel: &Kernel, device: &Device, global: &[usize]) -> Vec<Vec<usize>> {
+    let max_group = cmp::min(
+        device.get_info::<device::information::MaxWorkGroupSize>(),
+        kernel.get_work_group_info::<information::WorkGroupSize>(device)
+    );
+    let multiple = kernel.get_work_group_info::<information::PreferredWorkGroupSizeMultiple>(device);
+
+    candidate_space(
+        global,
+        &device.get_info::<device::information::MaxWorkItemSizes>(),
+        max_group,
+        cmp::max(multiple, 1)
+    )
+}
+
+fn candidate_space(global: &[usize], max_items: &[usize], max_group: usize, multiple: usize)
+    -> Vec<Vec<usize>>
+{
+    let mut space = vec![Vec::new()];
+
+    for (dimension, &size) in global.iter().enumerate() {
+        let limit = cmp::min(cmp::min(size, max_group), max_items.get(dimension).cloned().unwrap_or(1));
+        let sizes: Vec<usize> = (1..limit + 1).filter(|&local| {
+            size % local == 0
+                && (local.is_power_of_two() || (dimension == 0 && local % multiple == 0))
+        }).collect();
+
+        space = space.into_iter().flat_map(|prefix: Vec<usize>| {
+            sizes.iter().map(move |&local| {
+                let mut candidate = prefix.clone();
+                candidate.push(local);
+                candidate
+            })
+        }).collect();
+    }
+
+    space.retain(|candidate| candidate.iter().product::<usize>() <= max_group);
+    space
+}
+
+/// An auto-tuner for the local size of kernels. Each candidate given by `candidates` is
+/// launched on a command queue with profiling enabled, and the fastest one is kept, keyed by
+/// device name, driver version and kernel name. Results can be persisted through
+/// `Tuner::save` and `Tuner::load`.
+///
+/// # Examples
+/// ```no_run
+/// # extern crate gprust;
+/// use gprust::{Context, Device, CommandQueue, Buffer, program, mem, command_queue, Future};
+/// use gprust::kernel::tuner::Tuner;
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let context = Context::default().ok_or("no default context")?;
+/// let device = Device::default().ok_or("no default device")?;
+/// let properties = command_queue::PropertiesBuilder::new().profiling().finish();
+/// let queue = CommandQueue::create(&context, &device, properties)
+///     .map_err(|_| "profiling not supported")?;
+///
+/// let program = program::Builder::create_with_sources(
+///     Some("__kernel void twice(__global float * data) {
+///         data[get_global_id(0)] *= 2;
+///     }"),
+///     &context
+/// ).expect("I did provide a source");
+/// let program = program.build().wait().map_err(|_| "build failed")?;
+/// let mut kernel = program.create_kernel("twice").map_err(|_| "no such kernel")?;
+/// let data = Buffer::create(vec![1.0f32; 1 << 20], &context, mem::Flags::new())
+///     .map_err(|_| "failed to create buffer")?;
+/// kernel.set_arg(0, &data).map_err(|_| "invalid argument")?;
+///
+/// let mut tuner = Tuner::load("tuning.tsv").map_err(|_| "failed to read tuning results")?;
+/// let range = tuner.tune(&queue, &kernel, &[1 << 20]).map_err(|_| "tuning failed")?;
+/// tuner.save("tuning.tsv").map_err(|_| "failed to write tuning results")?;
+///
+/// queue.enqueue_nd_range(&kernel, &range).map_err(|_| "failed to launch kernel")?;
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tuner {
+    results: HashMap<Key, Vec<usize>>,
+    repetitions_field: usize,
+}
+
+impl Tuner {
+    /// Create a tuner without any result. Each candidate is launched 3 times by default.
+    pub fn new() -> Self {
+        Tuner {
+            results: HashMap::new(),
+            repetitions_field: 3,
+        }
+    }
+
+    /// Create a tuner with the results saved at `path` by `Tuner::save`. If the file does not
+    /// exist, the tuner has no result.
+    ///
+    /// # Errors
+    /// Any I/O error other than `io::ErrorKind::NotFound`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut tuner = Tuner::new();
+
+        let mut content = String::new();
+        match fs::File::open(path) {
+            Ok(mut file) => file.read_to_string(&mut content)?,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(tuner),
+            Err(err) => return Err(err),
+        };
+
+        // Malformed lines are ignored, they would only cause a new tuning.
+        for line in content.lines() {
+            let fields: Vec<_> = line.split('\t').collect();
+            if fields.len() != 4 {
+                continue;
+            }
+
+            let local: Result<Vec<usize>, _> = fields[3].split(',').map(|s| s.parse()).collect();
+            if let Ok(local) = local {
+                let key = Key {
+                    device: fields[0].to_owned(),
+                    driver: fields[1].to_owned(),
+                    kernel: fields[2].to_owned(),
+                };
+                tuner.results.insert(key, local);
+            }
+        }
+
+        Ok(tuner)
+    }
+
+    /// Write the results to `path`, one tab-separated line per device and kernel.
+    ///
+    /// # Errors
+    /// Any I/O error.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut results: Vec<_> = self.results.iter().collect();
+        results.sort();
+
+        let mut file = fs::File::create(path)?;
+        for (key, local) in results {
+            let local: Vec<_> = local.iter().map(|size| size.to_string()).collect();
+            writeln!(file, "{}\t{}\t{}\t{}", key.device, key.driver, key.kernel, local.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the number of times each candidate is launched.
+    pub fn repetitions(&self) -> usize {
+        self.repetitions_field
+    }
+
+    /// Set the number of times each candidate is launched, the fastest launch being kept.
+    pub fn set_repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions_field = cmp::max(repetitions, 1);
+        self
+    }
+
+    /// Return the best local size found for `kernel` on `device` if any.
+    ///
+    /// # Panics
+    /// Same as `Kernel::get_info`.
+    pub fn get(&self, device: &Device, kernel: &Kernel) -> Option<&[usize]> {
+        self.results.get(&Key::new(device, kernel)).map(|local| &local[..])
+    }
+
+    /// Return a range over `global` with the best local size for `kernel` on the device of
+    /// `queue`. If no result is known for this device and kernel, or if the known local size
+    /// does not divide `global`, each candidate is launched on `queue`: the arguments of `kernel`
+    /// must be set beforehand, and the buffers it writes to will be modified.
+    ///
+    /// # Errors
+    /// * `TuneError::ProfilingNotEnabled` if `queue` does not have profiling enabled.
+    /// * `TuneError::InvalidGlobalSize` if `global` is not a valid global size.
+    /// * `TuneError::NoCandidate` if no candidate could be launched.
+    /// * `TuneError::Launch(err)` or `TuneError::Execution(err)` if a candidate launch failed
+    /// for another reason than its local size.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_nd_range`.
+    pub fn tune(&mut self, queue: &CommandQueue, kernel: &Kernel, global: &[usize])
+        -> Result<NdRange, TuneError>
+    {
+        if global.is_empty() || global.len() > 3 || global.contains(&0) {
+            return Err(TuneError::InvalidGlobalSize);
+        }
+
+        let device = queue.get_info::<command_queue::information::Device>();
+        let key = Key::new(&device, kernel);
+
+        if let Some(local) = self.results.get(&key) {
+            let divides = local.len() == global.len()
+                          && global.iter().zip(local).all(|(size, local)| size % local == 0);
+            if divides {
+                return Ok(NdRange::new(global).set_local(local));
+            }
+        }
+
+        if !queue.get_info::<command_queue::information::Properties>().profiling() {
+            return Err(TuneError::ProfilingNotEnabled);
+        }
+
+        let mut best: Option<(ffi::cl_ulong, Vec<usize>)> = None;
+        for candidate in candidates(kernel, &device, global) {
+            let range = NdRange::new(global).set_local(&candidate);
+            let mut fastest = None;
+
+            for _ in 0..self.repetitions_field {
+                let event = match queue.enqueue_nd_range(kernel, &range) {
+                    Ok(event) => event,
+                    Err(LaunchError::InvalidWorkGroupSize) | Err(LaunchError::AllocationFailure) => break,
+                    Err(err) => return Err(TuneError::Launch(err)),
+                };
+                event.wait_for_completion().map_err(TuneError::Execution)?;
+
+                if let Ok(profile) = event.profile() {
+                    let elapsed = profile.end().saturating_sub(profile.start());
+                    fastest = Some(fastest.map_or(elapsed, |fastest| cmp::min(fastest, elapsed)));
+                }
+            }
+
+            match (fastest, &best) {
+                (Some(elapsed), &Some((best_elapsed, _))) if elapsed >= best_elapsed => (),
+                (Some(elapsed), _) => best = Some((elapsed, candidate)),
+                (None, _) => (),
+            }
+        }
+
+        let (_, local) = best.ok_or(TuneError::NoCandidate)?;
+        let range = NdRange::new(global).set_local(&local);
+        self.results.insert(key, local);
+        Ok(range)
+    }
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        Tuner::new()
+    }
+}
+
+#[test]
+fn test_candidate_space() {
+    assert_eq!(
+        candidate_space(&[96], &[64], 256, 24),
+        vec![vec![1], vec![2], vec![4], vec![8], vec![16], vec![24], vec![32], vec![48]]
+    );
+
+    let space = candidate_space(&[64, 8], &[256, 4], 32, 32);
+    assert!(space.iter().all(|c| c[0] * c[1] <= 32 && c[1] <= 4));
+    assert!(space.contains(&vec![8, 4]));
+    assert!(!space.contains(&vec![16, 4]));
+
+    assert_eq!(candidate_space(&[7], &[8], 8, 1), vec![vec![1], vec![7]]);
+}
+
+#[test]
+fn test_persistence() {
+    let mut tuner = Tuner::new();
+    let key = Key {
+        device: "Some GPU".to_owned(),
+        driver: "1.2.3".to_owned(),
+        kernel: "saxpy".to_owned(),
+    };
+    tuner.results.insert(key, vec![64, 2]);
+
+    let path = ::std::env::temp_dir().join("gprust_test_tuner_persistence.tsv");
+    tuner.save(&path).unwrap();
+    assert_eq!(Tuner::load(&path).unwrap(), tuner);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(Tuner::load(&path).unwrap(), Tuner::new());
+}
+
+#[test]
+fn test_tune() {
+    use wrapper::types::{context, program, mem};
+    use futures::Future;
+
+    let context = context::Context::default().unwrap();
+    let device = Device::default().unwrap();
+    let properties = command_queue::PropertiesBuilder::new().profiling().finish();
+    let queue = CommandQueue::create(&context, &device, properties).unwrap();
+
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void twice(__global float * data) {
+            data[get_global_id(0)] *= 2;
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+    let mut kernel = program.create_kernel("twice").unwrap();
+    let data = mem::Buffer::create(vec![1.0f32; 1024], &context, mem::Flags::new()).unwrap();
+    kernel.set_arg(0, &data).unwrap();
+
+    let mut tuner = Tuner::new().set_repetitions(1);
+    let range = tuner.tune(&queue, &kernel, &[1024]).unwrap();
+    assert_eq!(tuner.get(&device, &kernel), range.local());
+    assert_eq!(tuner.tune(&queue, &kernel, &[1024]).unwrap(), range);
+
+    let queue = CommandQueue::create(&context, &device, command_queue::Properties::new()).unwrap();
+    assert_eq!(Tuner::new().tune(&queue, &kernel, &[1024]), Err(TuneError::ProfilingNotEnabled));
+}
@@ -0,0 +1,224 @@
+//! A module defining `TypedKernel`, a kernel whose signature is known statically.
+
+use wrapper::ffi;
+use wrapper::types::command_queue::{WaitList, NdRange, LaunchError};
+use wrapper::types::event::Event;
+use super::{Kernel, CreationError, information};
+use super::arg::{KernelArg, SetArgError};
+use std::marker::PhantomData;
+use std::fmt;
+
+/// A trait implemented by tuples of `KernelArg` values, describing the full list of arguments
+/// of a kernel.
+pub trait KernelArgs {
+    /// Return the number of arguments.
+    fn count() -> usize;
+
+    /// Set every argument on `kernel`, in order. On failure, return the index of the faulty
+    /// argument along with the error.
+    fn set(&self, kernel: &mut Kernel) -> Result<(), (ffi::cl_uint, SetArgError)>;
+}
+
+impl KernelArgs for () {
+    fn count() -> usize {
+        0
+    }
+
+    fn set(&self, _: &mut Kernel) -> Result<(), (ffi::cl_uint, SetArgError)> {
+        Ok(())
+    }
+}
+
+macro_rules! args_impl {
+    ($count: expr; $($name: ident => $index: tt),*) => {
+        impl<$($name: KernelArg),*> KernelArgs for ($($name,)*) {
+            fn count() -> usize {
+                $count
+            }
+
+            fn set(&self, kernel: &mut Kernel) -> Result<(), (ffi::cl_uint, SetArgError)> {
+                $(
+                kernel.set_arg($index, &self.$index).map_err(|err| ($index, err))?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+args_impl!(1; A => 0);
+args_impl!(2; A => 0, B => 1);
+args_impl!(3; A => 0, B => 1, C => 2);
+args_impl!(4; A => 0, B => 1, C => 2, D => 3);
+args_impl!(5; A => 0, B => 1, C => 2, D => 3, E => 4);
+args_impl!(6; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5);
+args_impl!(7; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6);
+args_impl!(8; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7);
+args_impl!(9; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7, I => 8);
+args_impl!(10; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7, I => 8, J => 9);
+args_impl!(11; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7, I => 8, J => 9, K => 10);
+args_impl!(12; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7, I => 8, J => 9, K => 10, L => 11);
+
+/// An error returned by `Program::typed_kernel` and `TypedKernel::new`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum TypedKernelError {
+    /// The kernel could not be created.
+    Creation(CreationError),
+
+    /// The number of arguments of the signature does not match the number of parameters of the
+    /// kernel.
+    ArityMismatch {
+        /// Number of parameters declared by the kernel.
+        parameters: usize,
+
+        /// Number of arguments in the signature.
+        signature: usize,
+    },
+}
+
+impl fmt::Display for TypedKernelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypedKernelError::Creation(err) =>
+                write!(f, "{}", err),
+            TypedKernelError::ArityMismatch { parameters, signature } =>
+                write!(f, "the kernel has {} parameters but the signature has {} arguments",
+                       parameters, signature),
+        }
+    }
+}
+
+/// A kernel with a statically known signature `A`, a tuple of `KernelArg` types such as
+/// `(Buffer<f32>, Buffer<f32>, u32)`. All the arguments are given at once on each launch.
+///
+/// If the program was built with `-cl-kernel-arg-info`, argument validation is enabled (see
+/// `Kernel::enable_validation`) so that type mismatches are reported on the first launch.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{CommandQueue, Buffer, program, mem, Future};
+/// use gprust::command_queue::{self, NdRange};
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let queue = CommandQueue::default().ok_or("no default command queue")?;
+/// let context = queue.get_info::<command_queue::information::Context>();
+/// let program = program::Builder::create_with_sources(
+///     Some("__kernel void saxpy(__global const float * x, __global float * y, float a) {
+///         y[get_global_id(0)] += a * x[get_global_id(0)];
+///     }"),
+///     &context
+/// ).expect("I did provide a source");
+/// let program = program.build().wait().map_err(|_| "build failed")?;
+///
+/// let mut saxpy = program.typed_kernel::<(Buffer<f32>, Buffer<f32>, f32)>("saxpy")
+///                        .map_err(|_| "signature mismatch")?;
+///
+/// let x = Buffer::create(vec![1.0f32; 16], &context, mem::Flags::new())
+///     .map_err(|_| "failed to create buffer")?;
+/// let y = Buffer::create(vec![0.0f32; 16], &context, mem::Flags::new())
+///     .map_err(|_| "failed to create buffer")?;
+///
+/// let event = saxpy.launch(&queue, &NdRange::new(&[16]), (x, y, 2.0))
+///                  .map_err(|_| "failed to launch kernel")?;
+/// event.wait().map_err(|_| "kernel failed")?;
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+pub struct TypedKernel<A> {
+    kernel: Kernel,
+    phantom: PhantomData<fn(A)>,
+}
+
+impl<A: KernelArgs> TypedKernel<A> {
+    /// Wrap `kernel`, checking that its number of parameters matches the signature `A`.
+    ///
+    /// # Errors
+    /// `TypedKernelError::ArityMismatch { .. }` if the number of parameters does not match.
+    ///
+    /// # Panics
+    /// Same as `Kernel::get_info`.
+    pub fn new(mut kernel: Kernel) -> Result<Self, TypedKernelError> {
+        let parameters = kernel.get_info::<information::NumArgs>() as usize;
+        if parameters != A::count() {
+            return Err(TypedKernelError::ArityMismatch { parameters, signature: A::count() });
+        }
+
+        // Without argument information, only the arity can be checked.
+        let _ = kernel.enable_validation();
+
+        Ok(TypedKernel {
+            kernel,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Set all the arguments to `args` and enqueue the kernel over `range` on `queue`, which is
+    /// either a `&CommandQueue` or a `WaitList` returned by `CommandQueue::after`.
+    ///
+    /// # Errors
+    /// * `LaunchError::InvalidArg { .. }` if one of the arguments was rejected by the driver.
+    /// * Same as `WaitList::enqueue_nd_range` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_nd_range`.
+    pub fn launch<'a, Q: Into<WaitList<'a>>>(&mut self, queue: Q, range: &NdRange, args: A)
+        -> Result<Event, LaunchError>
+    {
+        args.set(&mut self.kernel).map_err(|(index, error)| LaunchError::InvalidArg { index, error })?;
+        queue.into().enqueue_nd_range(&self.kernel, range)
+    }
+
+    /// Create an independent typed kernel for the same kernel function, see
+    /// `Kernel::duplicate`.
+    pub fn duplicate(&self) -> Self {
+        TypedKernel {
+            kernel: self.kernel.duplicate(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the underlying kernel.
+    pub fn kernel(&self) -> &Kernel {
+        &self.kernel
+    }
+
+    /// Unwrap the underlying kernel.
+    pub fn into_kernel(self) -> Kernel {
+        self.kernel
+    }
+}
+
+impl<A> fmt::Debug for TypedKernel<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("TypedKernel")
+         .field(&self.kernel)
+         .finish()
+    }
+}
+
+#[test]
+fn test_arity() {
+    use wrapper::types::{context, program, mem};
+    use futures::Future;
+
+    let context = context::Context::default().unwrap();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void saxpy(__global const float * x, __global float * y, float a) {
+            y[get_global_id(0)] += a * x[get_global_id(0)];
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+
+    assert_eq!(
+        program.typed_kernel::<(mem::Buffer<f32>, f32)>("saxpy").unwrap_err(),
+        TypedKernelError::ArityMismatch { parameters: 3, signature: 2 }
+    );
+    assert_eq!(
+        program.typed_kernel::<()>("axpy").unwrap_err(),
+        TypedKernelError::Creation(CreationError::InvalidKernelName)
+    );
+    assert!(program.typed_kernel::<(mem::Buffer<f32>, mem::Buffer<f32>, f32)>("saxpy").is_ok());
+}
@@ -0,0 +1,301 @@
+//! A module defining `ChunkedBuffer`, for buffers larger than what a device can allocate at once.
+
+use super::{Buffer, Flags, CreationError};
+use wrapper::types::context::{self, Context};
+use wrapper::types::device;
+use wrapper::types::command_queue::{CommandQueue, TransferError};
+use wrapper::types::event::Event;
+use std::iter::{IntoIterator, ExactSizeIterator};
+use std::ops::Range;
+use std::mem;
+use std::fmt;
+
+/// A logical buffer split across several buffers, the chunks, so that no chunk exceeds the
+/// maximum allocation size of the devices of the context
+/// (`device::information::MaxMemAllocSize`). Every chunk holds `chunk_len()` elements, except
+/// the last one which may be shorter.
+///
+/// Host reads and writes may span several chunks. Kernels see one chunk at a time: they are
+/// launched chunk by chunk, using `ChunkedBuffer::chunks` to get each chunk together with its
+/// offset in the logical buffer.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{CommandQueue, mem, command_queue};
+/// use gprust::mem::chunked::ChunkedBuffer;
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let queue = CommandQueue::default().ok_or("no default command queue")?;
+/// let context = queue.get_info::<command_queue::information::Context>();
+/// let data = ChunkedBuffer::create(vec![0.0f32; 1 << 20], &context, mem::Flags::new())
+///     .map_err(|_| "failed to create buffer")?;
+///
+/// for chunk in data.chunks() {
+///     /* launch a kernel on `chunk.buffer()`, passing `chunk.offset()` if needed */
+/// }
+///
+/// let mut tail = vec![0.0; 16];
+/// data.enqueue_read(&queue, data.len() - 16, &mut tail).map_err(|_| "read failed")?;
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+pub struct ChunkedBuffer<T> {
+    chunks: Vec<Buffer<T>>,
+    chunk_len: usize,
+    len: usize,
+}
+
+/// A chunk of a `ChunkedBuffer`, returned by `ChunkedBuffer::chunks`.
+pub struct Chunk<'a, T: 'a> {
+    buffer: &'a Buffer<T>,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a, T> Chunk<'a, T> {
+    /// Return the buffer holding the chunk.
+    pub fn buffer(&self) -> &'a Buffer<T> {
+        self.buffer
+    }
+
+    /// Return the index in the logical buffer of the first element of the chunk.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Return the number of elements of the chunk.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the chunk holds no element (never happens for a chunk returned by
+    /// `ChunkedBuffer::chunks`).
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a, T> Clone for Chunk<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Chunk<'a, T> { }
+
+impl<'a, T> fmt::Debug for Chunk<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Chunk")
+         .field("offset", &self.offset)
+         .field("len", &self.len)
+         .finish()
+    }
+}
+
+impl<T> ChunkedBuffer<T> {
+    /// Allocate a new chunked buffer from an iterable object, with chunks as large as the
+    /// devices of `context` allow. Properties of the chunks can be set through the `flags`
+    /// argument.
+    ///
+    /// # Errors
+    /// Same as `Buffer::create`, for any chunk.
+    ///
+    /// # Panics
+    /// Same as `Buffer::create`.
+    pub fn create<I>(data: I, context: &Context, flags: Flags) -> Result<Self, CreationError>
+        where I: IntoIterator<Item = T>, I::IntoIter: ExactSizeIterator
+    {
+        let max_alloc_size = context.get_info::<context::information::Devices>()
+                                    .iter()
+                                    .map(|d| d.get_info::<device::information::MaxMemAllocSize>())
+                                    .min()
+                                    .unwrap_or(0);
+        let chunk_len = max_alloc_size as usize / mem::size_of::<T>().max(1);
+        Self::create_with_chunk_len(data, chunk_len, context, flags)
+    }
+
+    /// Same as `ChunkedBuffer::create`, with chunks of `chunk_len` elements. `chunk_len` should
+    /// not exceed the maximum allocation size of the devices of `context`.
+    ///
+    /// # Errors
+    /// * `CreationError::NoData` if no data was provided or if `chunk_len == 0`.
+    /// * Same as `Buffer::create` otherwise, for any chunk.
+    ///
+    /// # Panics
+    /// Same as `Buffer::create`.
+    pub fn create_with_chunk_len<I>(data: I, chunk_len: usize, context: &Context, flags: Flags)
+        -> Result<Self, CreationError>
+        where I: IntoIterator<Item = T>, I::IntoIter: ExactSizeIterator
+    {
+        let mut data = data.into_iter();
+        let len = data.len();
+
+        if len == 0 || chunk_len == 0 {
+            return Err(CreationError::NoData);
+        }
+
+        // The number of chunks, rounded up since `len > 0`.
+        let mut chunks = Vec::with_capacity((len - 1) / chunk_len + 1);
+        while data.len() != 0 {
+            chunks.push(Buffer::create(data.by_ref().take(chunk_len), context, flags)?);
+        }
+
+        Ok(ChunkedBuffer {
+            chunks,
+            chunk_len,
+            len,
+        })
+    }
+
+    /// Return the number of elements of the logical buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the buffer holds no element (never happens for a successfully created
+    /// chunked buffer).
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the number of elements of a chunk (except maybe the last one).
+    pub fn chunk_len(&self) -> usize {
+        self.chunk_len
+    }
+
+    /// Return the chunks in the order of the logical buffer.
+    pub fn chunks(&self) -> Vec<Chunk<'_, T>> {
+        self.chunks.iter().enumerate().map(|(index, buffer)| {
+            let offset = index * self.chunk_len;
+            Chunk {
+                buffer,
+                offset,
+                len: self.chunk_len.min(self.len - offset),
+            }
+        }).collect()
+    }
+
+    // Split the range `offset..offset + len` of the logical buffer into a list of
+    // `(chunk index, offset in the chunk, range in the host slice)`.
+    fn spans(&self, offset: usize, len: usize)
+        -> Result<Vec<(usize, usize, Range<usize>)>, TransferError>
+    {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len => (),
+            _ => return Err(TransferError::OutOfBounds),
+        }
+
+        let mut spans = vec![];
+        let mut position = offset;
+        while position < offset + len {
+            let (index, chunk_offset) = (position / self.chunk_len, position % self.chunk_len);
+            let count = (self.chunk_len - chunk_offset).min(offset + len - position);
+            spans.push((index, chunk_offset, position - offset..position - offset + count));
+            position += count;
+        }
+        Ok(spans)
+    }
+
+    /// Enqueue commands writing `data` into the logical buffer starting at element `offset`,
+    /// one per chunk spanned. `data` is copied, so the commands do not borrow it.
+    ///
+    /// # Errors
+    /// * `TransferError::OutOfBounds` if the written range exceeds the logical buffer.
+    /// * Same as `CommandQueue::enqueue_write_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_write_buffer`.
+    pub fn enqueue_write(&self, queue: &CommandQueue, offset: usize, data: &[T])
+        -> Result<Vec<Event>, TransferError>
+        where T: Copy + Send + 'static
+    {
+        self.spans(offset, data.len())?.into_iter().map(|(index, chunk_offset, range)| {
+            queue.enqueue_write_buffer(&self.chunks[index], chunk_offset, &data[range])
+        }).collect()
+    }
+
+    /// Read elements of the logical buffer starting at element `offset` into `data`, blocking
+    /// until all the chunks spanned have been read.
+    ///
+    /// # Errors
+    /// * `TransferError::OutOfBounds` if the read range exceeds the logical buffer.
+    /// * Same as `CommandQueue::enqueue_read_buffer` otherwise.
+    ///
+    /// # Panics
+    /// Same as `CommandQueue::enqueue_read_buffer`.
+    pub fn enqueue_read(&self, queue: &CommandQueue, offset: usize, data: &mut [T])
+        -> Result<(), TransferError>
+        where T: Copy
+    {
+        for (index, chunk_offset, range) in self.spans(offset, data.len())? {
+            queue.enqueue_read_buffer(&self.chunks[index], chunk_offset, &mut data[range])?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for ChunkedBuffer<T> {
+    fn clone(&self) -> Self {
+        ChunkedBuffer {
+            chunks: self.chunks.clone(),
+            chunk_len: self.chunk_len,
+            len: self.len,
+        }
+    }
+}
+
+impl<T> fmt::Debug for ChunkedBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChunkedBuffer")
+         .field("len", &self.len)
+         .field("chunk_len", &self.chunk_len)
+         .field("chunks", &self.chunks.len())
+         .finish()
+    }
+}
+
+#[test]
+fn test_chunked() {
+    use wrapper::types::command_queue;
+    use futures::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let buffer = ChunkedBuffer::create_with_chunk_len(0..35, 10, &context, Flags::new()).unwrap();
+
+    let lens: Vec<_> = buffer.chunks().iter().map(|c| (c.offset(), c.len())).collect();
+    assert_eq!(lens, vec![(0, 10), (10, 10), (20, 10), (30, 5)]);
+    assert_eq!(buffer.chunks()[3].buffer().len(), 5);
+
+    let written: Vec<_> = (100..115).collect();
+    for event in buffer.enqueue_write(&queue, 8, &written).unwrap() {
+        event.wait().unwrap();
+    }
+
+    let mut data = vec![0; 35];
+    buffer.enqueue_read(&queue, 0, &mut data).unwrap();
+    let expected: Vec<_> = (0..8).chain(100..115).chain(23..35).collect();
+    assert_eq!(data, expected);
+
+    let mut data = [0; 2];
+    assert_eq!(buffer.enqueue_read(&queue, 34, &mut data), Err(TransferError::OutOfBounds));
+    assert!(!ChunkedBuffer::create(0..35, &context, Flags::new()).unwrap().chunks().is_empty());
+
+    // The host copy of the data is freed once the chunks are created.
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted(#[allow(dead_code)] i32);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let data = (0..35).map(Counted);
+    let buffer = ChunkedBuffer::create_with_chunk_len(data, 10, &context, Flags::new()).unwrap();
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 35);
+    drop(buffer);
+}
@@ -1,11 +1,19 @@
 //! A module defining the `cl_mem` related types, such as the high-level `Buffer` type.
 
+pub mod pool;
+pub mod chunked;
+
 use wrapper::ffi;
 use wrapper::types::context::Context;
+use wrapper::types::event;
+use wrapper::types::accounting;
+use wrapper::types::kernel::arg::{KernelArg, ArgKind, Scalar};
 use wrapper::information::InformationResult;
 use errors::*;
 use std::mem;
 use std::iter::{IntoIterator, ExactSizeIterator};
+use std::marker::PhantomData;
+use std::os::raw::c_void;
 use std::fmt;
 
 bitfield_builder!(
@@ -16,7 +24,8 @@ bitfield_builder!(
     [write_only, "write_only"] => ffi::CL_MEM_WRITE_ONLY,
     [host_write_only, "host_write_only"] => ffi::CL_MEM_HOST_WRITE_ONLY,
     [host_read_only, "host_read_only"] => ffi::CL_MEM_HOST_READ_ONLY,
-    [host_no_access, "host_no_access"] => ffi::CL_MEM_HOST_NO_ACCESS
+    [host_no_access, "host_no_access"] => ffi::CL_MEM_HOST_NO_ACCESS,
+    [alloc_host_ptr, "alloc_host_ptr"] => ffi::CL_MEM_ALLOC_HOST_PTR
 );
 
 pub mod information {
@@ -54,15 +63,18 @@ pub mod information {
     // Offset
 }
 
-/// `Buffer` is a high-level type which maps to the low-level `cl_mem` OpenCL type.
-/// An object of type `Buffer` acts as a ref-counted reference to an OpenCL memory object.
-/// Be careful: `Buffer` erases type information, so this means that collecting data back from
-/// a buffer will necessarily be unsafe. One should maybe use the `Array` type instead.
-#[derive(PartialEq, Eq)]
-pub struct Buffer {
+/// `Buffer<T>` is a high-level type which maps to the low-level `cl_mem` OpenCL type.
+/// An object of type `Buffer<T>` acts as a ref-counted reference to an OpenCL memory object
+/// holding elements of type `T`. The element type is only known on the host side: it is used for
+/// checking kernel arguments, see `Kernel::enable_validation`.
+pub struct Buffer<T> {
     buffer: ffi::cl_mem,
+    phantom: PhantomData<T>,
 }
 
+unsafe impl<T: Send> Send for Buffer<T> { }
+unsafe impl<T: Sync> Sync for Buffer<T> { }
+
 /// An error returned by `Buffer::create`.
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum CreationError {
@@ -89,7 +101,7 @@ impl fmt::Display for CreationError {
     }
 }
 
-impl Buffer {
+impl<T> Buffer<T> {
     /// Allocate a new buffer from an iterable object. Properties of the memory object can be set
     /// through the `flags` argument.
     ///
@@ -115,12 +127,13 @@ impl Buffer {
     /// * `CreationError::NoData` if no data was provided.
     /// * `CreationError::InvalidFlags(explanation)` if mutually exclusive fields were set.
     /// An explanation string is provided through `explanation`.
-    /// * `CreationError::AllocationFailure` if the allocation failed.
+    /// * `CreationError::AllocationFailure` if the allocation failed, or if it would exceed the
+    /// memory budget of the context (see `Context::set_memory_budget`).
     ///
     /// # Panics
     /// Panic if the host or a device fails to allocate resources.
     pub fn create<I>(data: I, context: &Context, mut flags: Flags) -> Result<Self, CreationError>
-        where I: IntoIterator, I::IntoIter: ExactSizeIterator
+        where I: IntoIterator<Item = T>, I::IntoIter: ExactSizeIterator
     {
         if (flags.read_write() && flags.read_only()) || (flags.read_write() && flags.write_only())
             || (flags.read_only() && flags.write_only())
@@ -143,12 +156,16 @@ impl Buffer {
         }
 
         let data = data.into_iter();
-        let size = mem::size_of::<I::Item>() * data.len();
+        let size = mem::size_of::<T>() * data.len();
 
         if size == 0 {
             return Err(CreationError::NoData);
         }
 
+        if !accounting::reserve(context, size) {
+            return Err(CreationError::AllocationFailure);
+        }
+
         let data: Vec<_> = data.collect();
         flags.bitfield |= ffi::CL_MEM_COPY_HOST_PTR;
 
@@ -158,20 +175,95 @@ impl Buffer {
                 context.underlying(),
                 flags.bitfield,
                 size,
-                Box::into_raw(data.into_boxed_slice()) as _,
+                data.as_ptr() as *mut c_void,
                 &mut error
             )
         };
 
+        // The driver has copied the data because of `CL_MEM_COPY_HOST_PTR`.
+        drop(data);
+
+        if error != ffi::CL_SUCCESS {
+            accounting::release(unsafe { context.underlying() as usize }, size);
+        }
+
         if error == ffi::CL_INVALID_BUFFER_SIZE || error == ffi::CL_MEM_OBJECT_ALLOCATION_FAILURE {
             return Err(CreationError::AllocationFailure);
         }
 
-        let result = catch_ffi(error).map(|()| Buffer { buffer });
-        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+        let result = catch_ffi(error).map(|()| Buffer { buffer, phantom: PhantomData });
+        let buffer = expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+
+        let context = unsafe { context.underlying() as usize };
+        buffer.on_destroy(move || accounting::release(context, size));
+        Ok(buffer)
+    }
+
+    pub(super) unsafe fn underlying(&self) -> ffi::cl_mem {
+        self.buffer
+    }
+
+    /// Return the number of elements of the buffer.
+    ///
+    /// # Panics
+    /// Same as `get_info`.
+    pub fn len(&self) -> usize {
+        self.get_info::<information::Size>() / mem::size_of::<T>()
+    }
+
+    /// Return `true` if the buffer holds no element.
+    ///
+    /// # Panics
+    /// Same as `get_info`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Register `callback` to be called when the runtime actually frees the memory object, i.e.
+    /// once every `Buffer` referencing it has been dropped and every command using it has
+    /// completed. This is the right moment for releasing host memory backing the buffer.
+    /// Callbacks registered on the same memory object are called in the reverse order of their
+    /// registration.
+    ///
+    /// The callback may be called from a thread owned by the runtime: it should return quickly
+    /// and must not call blocking OpenCL functions. A panic in `callback` is caught and ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate gprust;
+    /// use gprust::{Context, Buffer, mem};
+    /// use std::sync::mpsc;
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let buffer = Buffer::create(vec![1, 2, 3, 4], &context, mem::Flags::new())
+    ///     .map_err(|_| "failed to create buffer")?;
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// buffer.on_destroy(move || sender.send(()).unwrap());
+    /// drop(buffer);
+    /// receiver.recv().map_err(|_| "callback was not called")?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn on_destroy<F: FnOnce() + Send + 'static>(&self, callback: F) {
+        let callback: Box<Box<dyn FnOnce() + Send>> = Box::new(Box::new(callback));
+        let user_data = Box::into_raw(callback);
+        let error = unsafe {
+            ffi::clSetMemObjectDestructorCallback(self.buffer, Some(call), user_data as _)
+        };
+
+        if error != ffi::CL_SUCCESS {
+            drop(unsafe { Box::from_raw(user_data) });
+        }
+        expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
     }
 
-    /// Query an information to the buffer. `T` should be a marker type from the `information`
+    /// Query an information to the buffer. `U` should be a marker type from the `information`
     /// module.
     ///
     /// # Examples
@@ -194,12 +286,12 @@ impl Buffer {
     /// param is passed (should only happen when a user incorrectly implements
     /// `MemInformation` on their own or if the information is not supported on the buffer
     /// and cargo features have not been set correctly, otherwise it is a bug).
-    pub fn get_info<T: information::MemInformation>(&self) -> T::Result {
+    pub fn get_info<U: information::MemInformation>(&self) -> U::Result {
         let result = unsafe {
             InformationResult::get_info(|size, value, ret_size| {
                 ffi::clGetMemObjectInfo(
                     self.buffer,
-                    T::id(),
+                    U::id(),
                     size,
                     value as _,
                     ret_size
@@ -216,18 +308,91 @@ impl Buffer {
     }
 }
 
-impl Clone for Buffer {
+unsafe extern "C" fn call(_: ffi::cl_mem, user_data: *mut c_void) {
+    event::call_boxed(user_data);
+}
+
+/// A memory object, whatever the type of its elements. This lets commands such as
+/// `CommandQueue::migrate` take memory objects of different element types at once.
+///
+/// This trait is implemented by `Buffer<T>` and cannot be implemented outside of this crate.
+pub trait MemObject: sealed::Sealed { }
+
+pub(super) mod sealed {
+    use wrapper::ffi;
+
+    pub trait Sealed {
+        // Return the underlying memory object.
+        unsafe fn underlying_mem(&self) -> ffi::cl_mem;
+    }
+}
+
+impl<T> sealed::Sealed for Buffer<T> {
+    unsafe fn underlying_mem(&self) -> ffi::cl_mem {
+        self.buffer
+    }
+}
+
+impl<T> MemObject for Buffer<T> { }
+
+unsafe impl<T: Scalar> KernelArg for Buffer<T> {
+    fn kind(&self) -> ArgKind {
+        ArgKind::Buffer(T::type_name())
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of::<ffi::cl_mem>()
+    }
+
+    fn pointer(&self) -> *const c_void {
+        &self.buffer as *const ffi::cl_mem as _
+    }
+}
+
+impl<T> PartialEq for Buffer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.buffer == other.buffer
+    }
+}
+
+impl<T> Eq for Buffer<T> { }
+
+impl<T> Clone for Buffer<T> {
     fn clone(&self) -> Self {
         catch_ffi(unsafe { ffi::clRetainMemObject(self.buffer) }).unwrap();
 
         Buffer {
             buffer: self.buffer,
+            phantom: PhantomData,
         }
     }
 }
 
-impl Drop for Buffer {
+impl<T> Drop for Buffer<T> {
     fn drop(&mut self) {
         catch_ffi(unsafe { ffi::clReleaseMemObject(self.buffer) }).unwrap();
     }
 }
+
+#[test]
+fn test_on_destroy() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let context = Context::default().unwrap();
+    let buffer = Buffer::create(vec![1, 2, 3, 4], &context, Flags::new()).unwrap();
+    let clone = buffer.clone();
+
+    let (sender, receiver) = mpsc::channel();
+    let first = sender.clone();
+    buffer.on_destroy(move || first.send(1).unwrap());
+    buffer.on_destroy(move || sender.send(2).unwrap());
+
+    drop(buffer);
+    assert!(receiver.try_recv().is_err());
+
+    drop(clone);
+    let timeout = Duration::from_secs(5);
+    assert_eq!(receiver.recv_timeout(timeout), Ok(2));
+    assert_eq!(receiver.recv_timeout(timeout), Ok(1));
+}
@@ -0,0 +1,277 @@
+//! A module defining `BufferPool`, for reusing buffers instead of allocating them per request.
+
+use super::{Buffer, Flags, CreationError};
+use wrapper::types::context::Context;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::fmt;
+
+/// A pool of buffers of a given context, sorted by size class. Buffers are checked out through
+/// `BufferPool::get` and go back to the pool when the `PooledBuffer` handle is dropped, so that
+/// later requests of the same size class and with the same flags reuse them instead of
+/// allocating a new buffer. Buffers with different flags are never mixed.
+///
+/// The size classes are powers of two: a request for `len` elements is served by a buffer of
+/// `len.next_power_of_two()` elements. Note that a buffer taken from the pool keeps the contents
+/// written by its previous user.
+///
+/// A buffer goes back to the pool as soon as its `PooledBuffer` handle is dropped, even if
+/// commands enqueued by its previous user are still using it: commands enqueued by the next
+/// user should wait for them, or the previous user should wait for them before dropping the
+/// handle.
+///
+/// Idle buffers stay allocated until `BufferPool::trim` is called or the pool is dropped, and
+/// count in the memory usage of the context (see `Context::memory_usage`).
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{Context, mem};
+/// use gprust::mem::pool::BufferPool;
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let context = Context::default().ok_or("no default context")?;
+/// let pool = BufferPool::<f32>::new(&context);
+///
+/// for len in vec![1000, 1024, 600] {
+///     let buffer = pool.get(len, mem::Flags::new()).map_err(|_| "failed to create buffer")?;
+///     assert_eq!(buffer.len(), 1024);
+///     /* work with `buffer` */
+/// }
+///
+/// let stats = pool.stats();
+/// assert_eq!((stats.hits(), stats.misses()), (2, 1));
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+pub struct BufferPool<T> {
+    context: Context,
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    idle: HashMap<(usize, Flags), Vec<Buffer<T>>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<T> State<T> {
+    fn idle_bytes(&self) -> usize {
+        let idle_len: usize = self.idle.iter().map(|(&(len, _), idle)| len * idle.len()).sum();
+        idle_len * ::std::mem::size_of::<T>()
+    }
+}
+
+/// Statistics of a `BufferPool`, returned by `BufferPool::stats`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Stats {
+    hits: usize,
+    misses: usize,
+    idle_buffers: usize,
+    idle_bytes: usize,
+}
+
+impl Stats {
+    /// Return the number of requests served by an idle buffer.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Return the number of requests which needed a new buffer.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Return the number of idle buffers.
+    pub fn idle_buffers(&self) -> usize {
+        self.idle_buffers
+    }
+
+    /// Return the total size in bytes of the idle buffers.
+    pub fn idle_bytes(&self) -> usize {
+        self.idle_bytes
+    }
+}
+
+impl<T: Clone + Default> BufferPool<T> {
+    /// Create an empty pool allocating buffers in `context`.
+    pub fn new(context: &Context) -> Self {
+        BufferPool {
+            context: context.clone(),
+            state: Mutex::new(State {
+                idle: HashMap::new(),
+                hits: 0,
+                misses: 0,
+            }),
+        }
+    }
+
+    /// Check out a buffer holding at least `len` elements and created with `flags`, reusing an
+    /// idle one if any. A new buffer is filled with `T::default()`.
+    ///
+    /// # Errors
+    /// Same as `Buffer::create`.
+    ///
+    /// # Panics
+    /// Same as `Buffer::create`.
+    pub fn get(&self, len: usize, flags: Flags) -> Result<PooledBuffer<'_, T>, CreationError> {
+        if len == 0 {
+            return Err(CreationError::NoData);
+        }
+
+        let key = (len.next_power_of_two(), flags);
+        let buffer = {
+            let mut state = self.state.lock().unwrap();
+            let buffer = state.idle.get_mut(&key).and_then(|idle| idle.pop());
+            match buffer {
+                Some(_) => state.hits += 1,
+                None => state.misses += 1,
+            }
+            buffer
+        };
+
+        let buffer = match buffer {
+            Some(buffer) => buffer,
+            None => Buffer::create(vec![T::default(); key.0], &self.context, flags)?,
+        };
+
+        Ok(PooledBuffer {
+            buffer: Some(buffer),
+            key,
+            pool: self,
+        })
+    }
+}
+
+impl<T> BufferPool<T> {
+    /// Return the context in which buffers are allocated.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Return the statistics of the pool.
+    pub fn stats(&self) -> Stats {
+        let state = self.state.lock().unwrap();
+        let idle_buffers = state.idle.values().map(|idle| idle.len()).sum();
+
+        Stats {
+            hits: state.hits,
+            misses: state.misses,
+            idle_buffers,
+            idle_bytes: state.idle_bytes(),
+        }
+    }
+
+    /// Release idle buffers, largest first, until the idle buffers hold at most `max_idle_bytes`
+    /// bytes. Return the number of bytes released. Use `trim(0)` for releasing all idle buffers.
+    pub fn trim(&self, max_idle_bytes: usize) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let mut keys: Vec<_> = state.idle.keys().cloned().collect();
+        keys.sort_by_key(|&(len, _)| ::std::cmp::Reverse(len));
+
+        let element_size = ::std::mem::size_of::<T>();
+        let mut idle_bytes = state.idle_bytes();
+        let mut released = 0;
+
+        for key in keys {
+            let idle = state.idle.get_mut(&key).unwrap();
+            while idle_bytes > max_idle_bytes && idle.pop().is_some() {
+                idle_bytes -= key.0 * element_size;
+                released += key.0 * element_size;
+            }
+        }
+
+        state.idle.retain(|_, idle| !idle.is_empty());
+        released
+    }
+}
+
+impl<T> fmt::Debug for BufferPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferPool")
+         .field("context", &self.context)
+         .field("stats", &self.stats())
+         .finish()
+    }
+}
+
+/// A buffer checked out from a `BufferPool`. The buffer goes back to the pool on drop, see
+/// `BufferPool` for synchronizing with the commands using it.
+pub struct PooledBuffer<'a, T: 'a> {
+    buffer: Option<Buffer<T>>,
+
+    // The size class and the flags under which the buffer was checked out.
+    key: (usize, Flags),
+    pool: &'a BufferPool<T>,
+}
+
+impl<'a, T> PooledBuffer<'a, T> {
+    /// Take the buffer out of the pool for good: it will not go back to the pool.
+    pub fn detach(mut self) -> Buffer<T> {
+        self.buffer.take().unwrap()
+    }
+}
+
+impl<'a, T> Deref for PooledBuffer<'a, T> {
+    type Target = Buffer<T>;
+
+    fn deref(&self) -> &Buffer<T> {
+        self.buffer.as_ref().unwrap()
+    }
+}
+
+impl<'a, T> Drop for PooledBuffer<'a, T> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            // Do not panic while unwinding if another thread poisoned the lock.
+            if let Ok(mut state) = self.pool.state.lock() {
+                state.idle.entry(self.key).or_default().push(buffer);
+            }
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for PooledBuffer<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PooledBuffer")
+         .field(&self.buffer.as_ref().map(|buffer| buffer.len()))
+         .finish()
+    }
+}
+
+#[test]
+fn test_pool() {
+    use super::information;
+
+    let context = Context::default().unwrap();
+    let pool = BufferPool::<i32>::new(&context);
+    let read_only = super::FlagsBuilder::new().read_only().finish();
+
+    let first = pool.get(100, Flags::new()).unwrap();
+    assert_eq!(first.len(), 128);
+    drop(first);
+
+    // Same size class.
+    let second = pool.get(65, Flags::new()).unwrap();
+    // Different flags.
+    let third = pool.get(100, read_only).unwrap();
+    // Different size class.
+    let fourth = pool.get(200, Flags::new()).unwrap();
+    assert!(third.get_info::<information::Flags>().read_only());
+
+    let stats = pool.stats();
+    assert_eq!((stats.hits(), stats.misses(), stats.idle_buffers()), (1, 3, 0));
+
+    let detached = fourth.detach();
+    drop((second, third));
+    let stats = pool.stats();
+    assert_eq!((stats.idle_buffers(), stats.idle_bytes()), (2, 2 * 128 * 4));
+
+    assert_eq!(pool.trim(128 * 4), 128 * 4);
+    assert_eq!(pool.stats().idle_buffers(), 1);
+    assert_eq!(pool.trim(0), 128 * 4);
+    assert_eq!(pool.stats().idle_bytes(), 0);
+    assert_eq!(detached.len(), 256);
+}
@@ -193,3 +193,7 @@ pub mod command_queue;
 pub mod mem;
 pub mod program;
 pub mod kernel;
+pub mod event;
+pub mod command_list;
+pub mod stream;
+mod accounting;
@@ -0,0 +1,343 @@
+//! A module for parsing compiler diagnostics out of program build logs.
+//!
+//! OpenCL only gives access to the build log as a raw string, whose format depends on the
+//! vendor. Most implementations (Intel, AMD, NVIDIA, pocl, Apple) use a clang-based compiler
+//! and emit diagnostics of the form `file:line:column: severity: message`, while some older
+//! drivers use the EDG format `"file", line N: severity: message` or the MSVC-like format
+//! `file(line): severity: message`. All of these are recognized here.
+
+use std::fmt;
+
+/// Severity of a compiler diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// An error (including fatal errors), the build has failed.
+    Error,
+
+    /// A warning, the build may have succeeded.
+    Warning,
+
+    /// A note attached to a previous error or warning.
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+// Severity markers as emitted by the different compilers.
+const MARKERS: &[(&str, Severity)] = &[
+    ("catastrophic error", Severity::Error),
+    ("fatal error", Severity::Error),
+    ("error", Severity::Error),
+    ("warning", Severity::Warning),
+    ("remark", Severity::Note),
+    ("note", Severity::Note),
+];
+
+/// A single diagnostic emitted by the compiler.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    severity: Severity,
+    file: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+}
+
+impl Diagnostic {
+    /// Parse a single line of a build log. Return `None` if the line is not the header of a
+    /// diagnostic (e.g. source excerpts, carets or summaries such as `1 error generated.`).
+    pub fn parse(line: &str) -> Option<Diagnostic> {
+        let line = line.trim_end();
+
+        // Diagnostics without any location, e.g. `error: unknown argument: '-foo'`.
+        for &(marker, severity) in MARKERS {
+            if line.starts_with(marker) && line[marker.len()..].starts_with(": ") {
+                return Some(Diagnostic {
+                    severity,
+                    file: None,
+                    line: None,
+                    column: None,
+                    message: line[marker.len() + 2..].trim().to_owned(),
+                });
+            }
+        }
+
+        // Otherwise, the location is everything before the first severity marker: picking the
+        // first one avoids being fooled by markers appearing in the message itself.
+        let (index, marker, severity) = MARKERS.iter()
+            .filter_map(|&(marker, severity)| {
+                line.find(&format!(": {}: ", marker)).map(|index| (index, marker, severity))
+            })
+            .min_by_key(|&(index, _, _)| index)?;
+
+        let (file, line_number, column) = parse_location(&line[..index])?;
+        Some(Diagnostic {
+            severity,
+            file,
+            line: line_number,
+            column,
+            message: line[index + marker.len() + 4..].trim().to_owned(),
+        })
+    }
+
+    /// Return the severity of the diagnostic.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Return the file name reported by the compiler if any. Depending on the vendor, this may
+    /// be a placeholder such as `<source>`, `<kernel>` or a temporary file path.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Return the (1-based) line number if any.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Return the (1-based) column number if any.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// Return the diagnostic message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    // Move the diagnostic to another location, keeping the column.
+    pub(super) fn relocate(&mut self, file: String, line: usize) {
+        self.file = Some(file);
+        self.line = Some(line);
+    }
+
+    /// Render the diagnostic together with the offending line of `source` and a caret pointing
+    /// at the reported column. `source` should be the full source the program was built from,
+    /// as given by `program::information::Source`. If the location cannot be found in `source`,
+    /// only the diagnostic header is rendered.
+    pub fn render(&self, source: &str) -> String {
+        let mut rendered = format!("{}\n", self);
+
+        let line = match self.line.and_then(|n| if n == 0 { None } else { source.lines().nth(n - 1) }) {
+            Some(line) => line,
+            None => return rendered,
+        };
+
+        rendered.push_str(line);
+        rendered.push('\n');
+
+        if let Some(column) = self.column {
+            // Keep tabs so that the caret lines up with the source line.
+            let padding: String = line.chars()
+                                      .take(column.saturating_sub(1))
+                                      .map(|c| if c == '\t' { '\t' } else { ' ' })
+                                      .collect();
+            rendered.push_str(&padding);
+            rendered.push_str("^\n");
+        }
+
+        rendered
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}:", file)?;
+        }
+
+        if let Some(line) = self.line {
+            write!(f, "{}:", line)?;
+            if let Some(column) = self.column {
+                write!(f, "{}:", column)?;
+            }
+        }
+
+        if self.file.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+// Parse the location prefix of a diagnostic, returning `None` if it is not a location at all.
+fn parse_location(location: &str) -> Option<(Option<String>, Option<usize>, Option<usize>)> {
+    let location = location.trim();
+
+    // EDG format: `"file", line 3`.
+    if let Some(quoted) = location.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        let line = quoted[end + 1..].trim_start().trim_start_matches(',').trim_start();
+        let line = line.strip_prefix("line ")?.trim().parse().ok()?;
+        return Some((non_empty(&quoted[..end]), Some(line), None));
+    }
+
+    // MSVC-like format: `file(3)` or `file(3,5)`.
+    if location.ends_with(')') {
+        let start = location.rfind('(')?;
+        let mut numbers = location[start + 1..location.len() - 1].split(',');
+        let line = numbers.next()?.trim().parse().ok()?;
+        let column = match numbers.next() {
+            Some(column) => Some(column.trim().parse().ok()?),
+            None => None,
+        };
+        return Some((non_empty(&location[..start]), Some(line), column));
+    }
+
+    // Clang format: `file:3:5` or `file:3`. The file name may itself contain colons.
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next()?;
+    let second = parts.next();
+    let first = parts.next();
+
+    let last_number = last.parse().ok();
+    let second_number = second.and_then(|s| s.parse().ok());
+
+    match (first, second_number, last_number) {
+        (Some(file), Some(line), Some(column)) => Some((non_empty(file), Some(line), Some(column))),
+        (None, Some(line), Some(column)) => Some((None, Some(line), Some(column))),
+        (_, None, Some(line)) => {
+            let file = match (first, second) {
+                (Some(first), Some(second)) => format!("{}:{}", first, second),
+                (None, Some(second)) => second.to_owned(),
+                _ => String::new(),
+            };
+            Some((non_empty(&file), Some(line), None))
+        }
+        _ => None,
+    }
+}
+
+fn non_empty(file: &str) -> Option<String> {
+    let file = file.trim();
+    if file.is_empty() { None } else { Some(file.to_owned()) }
+}
+
+/// A parsed build log, keeping the raw log alongside the diagnostics found in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    raw: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Log {
+    /// Parse a raw build log.
+    pub fn parse(raw: String) -> Log {
+        let diagnostics = raw.lines().filter_map(Diagnostic::parse).collect();
+        Log {
+            raw,
+            diagnostics,
+        }
+    }
+
+    /// Return the raw build log, as given by `program::information::BuildLog`.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Return all the diagnostics found in the log, in order of appearance.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Return the diagnostics of severity `Severity::Error`.
+    pub fn errors(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error).collect()
+    }
+
+    /// Return the diagnostics of severity `Severity::Warning`.
+    pub fn warnings(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning).collect()
+    }
+
+    pub(super) fn diagnostics_mut(&mut self) -> &mut [Diagnostic] {
+        &mut self.diagnostics
+    }
+
+    /// Render every diagnostic through `Diagnostic::render`.
+    pub fn render(&self, source: &str) -> String {
+        self.diagnostics.iter().map(|d| d.render(source)).collect()
+    }
+}
+
+impl fmt::Display for Log {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+#[test]
+fn test_parse_clang() {
+    let diagnostic = Diagnostic::parse("<source>:3:9: error: use of undeclared identifier 'x'");
+    assert_eq!(
+        diagnostic,
+        Some(Diagnostic {
+            severity: Severity::Error,
+            file: Some("<source>".to_owned()),
+            line: Some(3),
+            column: Some(9),
+            message: "use of undeclared identifier 'x'".to_owned(),
+        })
+    );
+
+    // Intel reports the source index in place of the file name.
+    let diagnostic = Diagnostic::parse("1:2:5: warning: unused variable 'y'").unwrap();
+    assert_eq!(diagnostic.severity(), Severity::Warning);
+    assert_eq!(diagnostic.file(), Some("1"));
+    assert_eq!((diagnostic.line(), diagnostic.column()), (Some(2), Some(5)));
+
+    let diagnostic = Diagnostic::parse("C:\\tmp\\OCL1.cl:4:1: fatal error: 'x.h' file not found").unwrap();
+    assert_eq!(diagnostic.severity(), Severity::Error);
+    assert_eq!(diagnostic.file(), Some("C:\\tmp\\OCL1.cl"));
+    assert_eq!(diagnostic.message(), "'x.h' file not found");
+}
+
+#[test]
+fn test_parse_other_formats() {
+    let diagnostic = Diagnostic::parse("\"/tmp/OCL1234.cl\", line 7: error: identifier \"x\" is undefined").unwrap();
+    assert_eq!(diagnostic.file(), Some("/tmp/OCL1234.cl"));
+    assert_eq!((diagnostic.line(), diagnostic.column()), (Some(7), None));
+    assert_eq!(diagnostic.message(), "identifier \"x\" is undefined");
+
+    let diagnostic = Diagnostic::parse("<kernel>(12): warning: variable \"i\" was declared but never referenced").unwrap();
+    assert_eq!(diagnostic.file(), Some("<kernel>"));
+    assert_eq!(diagnostic.line(), Some(12));
+
+    let diagnostic = Diagnostic::parse("error: unknown argument: '-foo'").unwrap();
+    assert_eq!(diagnostic.file(), None);
+    assert_eq!(diagnostic.line(), None);
+}
+
+#[test]
+fn test_parse_log() {
+    let log = Log::parse(
+        "<source>:2:5: warning: unused variable 'y'\n    int y;\n    ^\n\
+         <source>:3:9: error: use of undeclared identifier 'x'\n\
+         <source>:1:1: note: previous definition is here\n\
+         1 warning and 1 error generated.\n".to_owned()
+    );
+
+    assert_eq!(log.diagnostics().len(), 3);
+    assert_eq!(log.errors().len(), 1);
+    assert_eq!(log.warnings().len(), 1);
+}
+
+#[test]
+fn test_render() {
+    let source = "__kernel void f() {\n\tint y = x;\n}";
+    let diagnostic = Diagnostic::parse("<source>:2:10: error: use of undeclared identifier 'x'").unwrap();
+    assert_eq!(
+        diagnostic.render(source),
+        "<source>:2:10: error: use of undeclared identifier 'x'\n\tint y = x;\n\t        ^\n"
+    );
+}
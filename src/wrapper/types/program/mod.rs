@@ -0,0 +1,938 @@
+//! A module defining the `cl_program` related types, such as the high-level `Program` type.
+
+pub mod diagnostic;
+pub mod options;
+pub mod source;
+
+use wrapper::ffi;
+use wrapper::types::context::Context;
+use wrapper::types::device::Device;
+use wrapper::types::kernel::{self, Kernel};
+use wrapper::types::kernel::typed::{KernelArgs, TypedKernel, TypedKernelError};
+use wrapper::information::InformationResult;
+use errors::*;
+use std::ptr;
+use futures::{Poll, Future, Async};
+use std::ffi::CString;
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::fmt;
+
+enumz!(
+    BuildStatus,
+    ffi::cl_build_status,
+    "cl_build_status",
+    None => [ffi::CL_BUILD_NONE, "CL_BUILD_NONE"],
+    Error => [ffi::CL_BUILD_ERROR, "CL_BUILD_ERROR"],
+    Success => [ffi::CL_BUILD_SUCCESS, "CL_BUILD_SUCCESS"],
+    InProgress => [ffi::CL_BUILD_IN_PROGRESS, "CL_BUILD_IN_PROGRESS"]
+);
+
+enumz!(
+    BinaryType,
+    ffi::cl_program_binary_type,
+    "cl_program_binary_type",
+    None => [ffi::CL_PROGRAM_BINARY_TYPE_NONE, "CL_PROGRAM_BINARY_TYPE_NONE"],
+    CompiledObject => [ffi::CL_PROGRAM_BINARY_TYPE_COMPILED_OBJECT, "CL_PROGRAM_BINARY_TYPE_COMPILED_OBJECT"],
+    Library => [ffi::CL_PROGRAM_BINARY_TYPE_LIBRARY, "CL_PROGRAM_BINARY_TYPE_LIBRARY"],
+    Executable => [ffi::CL_PROGRAM_BINARY_TYPE_EXECUTABLE, "CL_PROGRAM_BINARY_TYPE_LIBRARY"]
+);
+
+pub mod information {
+    //! A module containing the information marker types for programs.
+
+    use wrapper::ffi;
+    use wrapper::information::*;
+    use wrapper::types::context;
+    use wrapper::types::device;
+
+    /// A trait implemented by marker types for retrieving information through `clGetProgramInfo`.
+    pub trait ProgramInformation: Information<ffi::cl_program_info> { }
+
+    macro_rules! test_fun {
+        ($test_fun: ident, $type: ident, $info_fun: ident) => {
+            #[test]
+            fn $test_fun() {
+                use futures::Future;
+
+                let context = context::Context::default().unwrap();
+                let program = super::Builder::create_with_sources(
+                    Some("__kernel void addFFT(__global float * filter, __global float * temp, float coeff) {
+                        int ind = get_global_id(0);
+                        filter[2 * ind] += temp[ind] * coeff;
+                    }"),
+                    &context
+                ).unwrap();
+                let program = program.build().wait().unwrap();
+                let _ = program.$info_fun::<$type>();
+            }
+        };
+    }
+
+    macro_rules! info_impl {
+        ($type: ident, $result: ty, $id: expr, $id_name: expr, $test_fun: ident) => {
+            general_info_impl!(ProgramInformation, ffi::cl_program_info, $type, $result, $id, $id_name);
+
+            test_fun!($test_fun, $type, get_info);
+        };
+    }
+
+    info_impl!(ReferenceCount, ffi::cl_uint, ffi::CL_PROGRAM_REFERENCE_COUNT, "CL_PROGRAM_REFERENCE_COUNT", test_reference_count);
+    info_impl!(Context, context::Context, ffi::CL_PROGRAM_CONTEXT, "CL_PROGRAM_CONTEXT", test_context);
+    info_impl!(NumDevices, ffi::cl_uint, ffi::CL_PROGRAM_NUM_DEVICES, "CL_PROGRAM_NUM_DEVICES", test_num_devices);
+    info_impl!(Devices, Vec<device::Device>, ffi::CL_PROGRAM_DEVICES, "CL_PROGRAM_DEVICES", test_devices);
+    info_impl!(Source, String, ffi::CL_PROGRAM_SOURCE, "CL_PROGRAM_SOURCE", test_source);
+    info_impl!(BinarySizes, Vec<usize>, ffi::CL_PROGRAM_BINARY_SIZES, "CL_PROGRAM_BINARY_SIZES", test_binary_sizes);
+    // ProgramBinaries
+    info_impl!(NumKernels, usize, ffi::CL_PROGRAM_NUM_KERNELS, "CL_PROGRAM_NUM_KERNELS", test_num_kernels);
+    info_impl!(KernelNames, String, ffi::CL_PROGRAM_KERNEL_NAMES, "CL_PROGRAM_KERNEL_NAMES", test_kernel_names);
+
+    /// A trait implemented by marker types for retrieving information through `clGetProgramBuildInfo`.
+    pub trait BuildInformation: Information<ffi::cl_program_build_info> { }
+
+    macro_rules! build_info_impl {
+        ($type: ident, $result: ty, $id: expr, $id_name: expr, $test_fun: ident) => {
+            general_info_impl!(BuildInformation, ffi::cl_program_build_info, $type, $result, $id, $id_name);
+
+            test_fun!($test_fun, $type, get_build_info);
+        };
+    }
+
+    build_info_impl!(BuildStatus, super::BuildStatus, ffi::CL_PROGRAM_BUILD_STATUS, "CL_PROGRAM_BUILD_STATUS", test_build_status);
+    build_info_impl!(BuildOptions, String, ffi::CL_PROGRAM_BUILD_OPTIONS, "CL_PROGRAM_BUILD_OPTIONS", test_build_options);
+    build_info_impl!(BuildLog, String, ffi::CL_PROGRAM_BUILD_LOG, "CL_PROGRAM_BUILD_LOG", test_build_log);
+    build_info_impl!(BinaryType, super::BinaryType, ffi::CL_PROGRAM_BINARY_TYPE, "CL_PROGRAM_BINARY_TYPE", test_binary_type);
+}
+
+/// `Program` is a high-level type which maps to the low-level `cl_program` OpenCL type.
+/// An object of type `Program` acts as a ref-counted reference to an OpenCL program.
+pub struct Program {
+    program: ffi::cl_program,
+
+    // Set when the program was created from a `SourceTree`, for remapping diagnostics.
+    resolved: Option<Arc<source::Resolved>>,
+}
+
+unsafe impl Send for Program { }
+unsafe impl Sync for Program { }
+
+/// A builder struct for `Program` type which compiles OpenCL programs.
+#[derive(PartialEq, Eq)]
+pub struct Builder {
+    program: Program,
+}
+
+unsafe impl Send for Builder { }
+unsafe impl Sync for Builder { }
+
+/// An error returned by `Builder::create_with_sources`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SourceError {
+    /// No sources were provided.
+    NoSources,
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SourceError::NoSources => write!(f, "no sources were provided"),
+        }
+    }
+}
+
+/// An error returned by `Builder::create_with_builtin_kernels`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum BuiltinKernelsError {
+    /// No device was provided.
+    NoDevice,
+
+    /// No kernel names were provided.
+    NoKernelNames,
+
+    /// The kernel named `kernel` is not a built-in kernel of the device named `device` (see
+    /// `Device::builtin_kernels`).
+    NotSupported {
+        /// Name of the requested kernel.
+        kernel: String,
+
+        /// Name of the device (`device::information::Name`).
+        device: String,
+    },
+
+    /// One of the devices was not associated with the context.
+    InvalidDevice,
+
+    /// The runtime rejected the kernel names, although the devices advertise them.
+    InvalidKernelNames,
+}
+
+impl fmt::Display for BuiltinKernelsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuiltinKernelsError::NoDevice =>
+                write!(f, "no device was provided"),
+            BuiltinKernelsError::NoKernelNames =>
+                write!(f, "no kernel names were provided"),
+            BuiltinKernelsError::NotSupported { ref kernel, ref device } =>
+                write!(f, "`{}` is not a built-in kernel of device `{}`", kernel, device),
+            BuiltinKernelsError::InvalidDevice =>
+                write!(f, "one of the devices was not associated with the context"),
+            BuiltinKernelsError::InvalidKernelNames =>
+                write!(f, "the runtime rejected the kernel names"),
+        }
+    }
+}
+
+/// An error returned by `FutureBuild`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum BuildError {
+    /// One of the devices does not have an available compiler.
+    CompilerNotAvailable,
+
+    /// The options provided are invalid.
+    InvalidBuildOptions,
+
+    /// The options provided through `Builder::build_with` did not pass validation, see
+    /// `BuildOptions::validate`.
+    UnsupportedBuildOptions(options::ValidationError),
+
+    /// Build failed, see build log.
+    BuildFailed(diagnostic::Log),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::CompilerNotAvailable
+                => write!(f, "a compiler was not available for one of the devices"),
+            BuildError::InvalidBuildOptions
+                => write!(f, "invalid build options"),
+            BuildError::UnsupportedBuildOptions(ref err)
+                => write!(f, "unsupported build options: {}", err),
+            BuildError::BuildFailed(ref log)
+                => write!(f, "build failed, log:\n{}", log),
+        }
+    }
+}
+
+/// A type containing the future result of a build.
+pub struct FutureBuild {
+    program: Result<Program, BuildError>,
+}
+
+impl Future for FutureBuild {
+    type Item = Program;
+    type Error = BuildError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        use std::mem;
+
+       if let Ok(ref program) = self.program {
+            match program.get_build_info::<information::BuildStatus>() {
+                BuildStatus::InProgress => return Ok(Async::NotReady),
+                BuildStatus::Error =>
+                    return Err(BuildError::BuildFailed(program.build_log())),
+                _ => (),
+            };
+        }
+
+        let program = mem::replace(
+            &mut self.program,
+            Err(BuildError::CompilerNotAvailable)
+        );
+
+        program.map(|program| Async::Ready(program))
+    }
+}
+
+extern "C" fn build_callback(program: ffi::cl_program, _: *mut ::std::os::raw::c_void) {
+    catch_ffi(unsafe { ffi::clReleaseProgram(program) }).unwrap();
+}
+
+impl Builder {
+    /// Start creating a program from an iterator of source strings for a given context.
+    ///
+    /// # Errors
+    /// * `SourceError::NoSources` if `sources` does not produce any elements.
+    ///
+    /// # Panics
+    /// Panics if the host or a device fails to allocate resources.
+    pub fn create_with_sources<'a, I>(sources: I, context: &Context) -> Result<Builder, SourceError>
+        where I: IntoIterator<Item = &'a str>
+    {
+        let (mut sources, lengths): (Vec<_>, Vec<_>) =
+            sources.into_iter()
+                   .map(|src| (src.as_ptr() as *const i8, src.len()))
+                   .unzip();
+        
+        if sources.len() == 0 {
+            return Err(SourceError::NoSources);
+        }
+        
+        let mut error = 0;
+        let program = unsafe {
+            ffi::clCreateProgramWithSource(
+                context.underlying(),
+                sources.len() as _,
+                sources.as_mut_ptr(),
+                lengths.as_ptr(),
+                &mut error
+            )
+        };
+
+        let result = catch_ffi(error).map(|()| Builder { program: Program { program, resolved: None } });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+
+    /// Start creating a program from the source `root` of `tree`, for a given context. Includes
+    /// are resolved by `tree` before creating the program, and the diagnostics of
+    /// `BuildError::BuildFailed` and of `Program::build_log` are reported against the original
+    /// sources.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate gprust;
+    /// use gprust::{Context, program, Future};
+    /// use gprust::program::source::SourceTree;
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let mut tree = SourceTree::new();
+    /// tree.add("scale.h", "#pragma once\n#define FACTOR 2")
+    ///     .add("main.cl", "#include \"scale.h\"
+    ///         __kernel void scale(__global float * buffer) {
+    ///             buffer[get_global_id(0)] *= FACTOR;
+    ///         }");
+    /// let program = program::Builder::create_with_source_tree(&tree, "main.cl", &context)
+    ///     .map_err(|_| "failed to resolve includes")?;
+    /// if let Ok(program) = program.build().wait() {
+    ///     /* do something with `program` */
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// Same as `SourceTree::resolve`.
+    ///
+    /// # Panics
+    /// Panics if the host or a device fails to allocate resources.
+    pub fn create_with_source_tree(tree: &source::SourceTree, root: &str, context: &Context)
+        -> Result<Builder, source::IncludeError>
+    {
+        let resolved = tree.resolve(root)?;
+        let mut builder = Builder::create_with_sources(Some(resolved.source()), context)
+            .expect("there is exactly one source");
+        builder.program.resolved = Some(Arc::new(resolved));
+        Ok(builder)
+    }
+
+    /// Create a program from built-in kernels of `devices`, for a given context. Since built-in
+    /// kernels do not need to be built, the program is returned directly and its kernels can be
+    /// created through `Program::create_kernel`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate gprust;
+    /// use gprust::{Context, Device, program};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let device = Device::default().ok_or("no default device")?;
+    /// let names: Vec<_> = device.builtin_kernels()
+    ///                           .into_iter()
+    ///                           .filter(|n| !n.trim().is_empty())
+    ///                           .collect();
+    ///
+    /// // Most devices do not have any built-in kernel.
+    /// if !names.is_empty() {
+    ///     if let Ok(program) = program::Builder::create_with_builtin_kernels(
+    ///         &context,
+    ///         Some(&device),
+    ///         names.iter().map(|n| n.as_str())
+    ///     ) {
+    ///         let kernels = program.create_kernels();
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `BuiltinKernelsError::NoDevice` if `devices` is empty.
+    /// * `BuiltinKernelsError::NoKernelNames` if `names` is empty once empty names are ignored.
+    /// * `BuiltinKernelsError::NotSupported { .. }` if one of the names is not a built-in
+    /// kernel of one of the devices.
+    /// * `BuiltinKernelsError::InvalidDevice` if one of the devices is not associated with
+    /// `context`.
+    /// * `BuiltinKernelsError::InvalidKernelNames` if the runtime rejects the names anyway.
+    ///
+    /// # Panics
+    /// Panics if the host or a device fails to allocate resources.
+    pub fn create_with_builtin_kernels<'a, 'b, D, N>(context: &Context, devices: D, names: N)
+        -> Result<Program, BuiltinKernelsError>
+        where D: IntoIterator<Item = &'a Device>, N: IntoIterator<Item = &'b str>
+    {
+        use wrapper::types::device;
+
+        let devices: Vec<_> = devices.into_iter().collect();
+        let names: Vec<_> = names.into_iter().map(|n| n.trim()).filter(|n| !n.is_empty()).collect();
+
+        if devices.is_empty() {
+            return Err(BuiltinKernelsError::NoDevice);
+        }
+
+        if names.is_empty() {
+            return Err(BuiltinKernelsError::NoKernelNames);
+        }
+
+        for d in &devices {
+            // A device without built-in kernels reports an empty name.
+            let supported = d.builtin_kernels();
+            let supported: Vec<_> = supported.iter()
+                                             .map(|s| s.trim())
+                                             .filter(|s| !s.is_empty())
+                                             .collect();
+            if let Some(kernel) = names.iter().find(|n| !supported.contains(n)) {
+                return Err(
+                    BuiltinKernelsError::NotSupported {
+                        kernel: kernel.to_string(),
+                        device: d.get_info::<device::information::Name>(),
+                    }
+                );
+            }
+        }
+
+        let device_ids: Vec<_> = devices.iter().map(|d| unsafe { d.underlying() }).collect();
+        // Names have been checked against the devices, so they cannot contain a null byte.
+        let names = CString::new(names.join(";")).expect("should be a valid string");
+
+        let mut error = 0;
+        let program = unsafe {
+            ffi::clCreateProgramWithBuiltInKernels(
+                context.underlying(),
+                device_ids.len() as _,
+                device_ids.as_ptr(),
+                names.as_ptr(),
+                &mut error
+            )
+        };
+
+        if error == ffi::CL_INVALID_DEVICE {
+            return Err(BuiltinKernelsError::InvalidDevice);
+        } else if error == ffi::CL_INVALID_VALUE {
+            return Err(BuiltinKernelsError::InvalidKernelNames);
+        }
+
+        let result = catch_ffi(error).map(|()| Program { program, resolved: None });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+
+    /// Build a program (i.e. compile + link) with specified `options`. The return value is a
+    /// future containing the program result.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate gprust;
+    /// use gprust::{Context, program, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void my_kernel(__global float * buffer) {
+    ///         buffer[get_global_id(0)] *= 2;
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// if let Ok(program) = program.build_with_options("-Werror").wait() {
+    ///     /* do something with `program` */
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// Errors that `FutureBuild` can return:
+    /// * `BuildError::CompilerNotAvailable` if one of the devices does not have an available compiler.
+    /// * `BuildError::InvalidBuildOptions` if the options string contained invalid options.
+    /// * `BuildError::BuildFailed(log)` if the build failed. The parsed build log can be get
+    /// through `log` or `Program::build_log`.
+    ///
+    /// # Panics
+    /// Panics if the host or a device fails to allocate resources.
+    pub fn build_with_options(self, options: &str) -> FutureBuild {
+        catch_ffi(unsafe { ffi::clRetainProgram(self.program.program) }).unwrap();
+        let err = unsafe {
+            ffi::clBuildProgram(
+                self.program.program,
+                0,
+                ptr::null(),
+                CString::new(options).expect("should be a valid string").as_ptr(),
+                Some(build_callback),
+                ptr::null_mut()
+            )
+        };
+
+        let result = if err == ffi::CL_INVALID_BUILD_OPTIONS {
+            Err(BuildError::InvalidBuildOptions)
+        } else if err == ffi::CL_COMPILER_NOT_AVAILABLE {
+            Err(BuildError::CompilerNotAvailable)
+        } else if err == ffi::CL_BUILD_PROGRAM_FAILURE {
+            Err(BuildError::BuildFailed(self.program.build_log()))
+        } else {
+            Ok(self.program)
+        };
+
+        expect!(catch_ffi(err), ffi::CL_OUT_OF_HOST_MEMORY, ffi::CL_OUT_OF_RESOURCES);
+
+        FutureBuild {
+            program: result,
+        }
+    }
+
+    /// Build a program with typed `options`. The options are first validated against the
+    /// devices associated with the program, then rendered and passed to `build_with_options`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate gprust;
+    /// use gprust::{Context, program, Future};
+    /// use gprust::program::options::{BuildOptions, Standard};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void scale(__global float * buffer) {
+    ///         buffer[get_global_id(0)] *= FACTOR;
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// let options = BuildOptions::new().define("FACTOR", 2)
+    ///                                  .set_standard(Standard::Cl11)
+    ///                                  .set_mad_enable();
+    /// if let Ok(program) = program.build_with(&options).wait() {
+    ///     /* do something with `program` */
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// Same as `build_with_options`, and additionally:
+    /// * `BuildError::UnsupportedBuildOptions(err)` if `options` did not pass validation.
+    ///
+    /// # Panics
+    /// Panics if the host or a device fails to allocate resources.
+    pub fn build_with(self, options: &options::BuildOptions) -> FutureBuild {
+        if let Err(err) = options.validate(&self.program.get_info::<information::Devices>()) {
+            return FutureBuild {
+                program: Err(BuildError::UnsupportedBuildOptions(err)),
+            };
+        }
+
+        self.build_with_options(&options.to_string())
+    }
+
+    /// Call `build_with_options` with an empty options string.
+    ///
+    /// # Errors
+    /// Errors that `FutureBuild` can return:
+    /// * `BuildErrorKind::CompilerNotAvailable` if one of the devices does not have an available compiler.
+    /// * `BuildErrorKind::InvalidBuildOptions` if the options string contained invalid options.
+    /// * `BuildErrorKind::BuildProgramFailure(log)` if the build failed. The parsed build log can
+    /// be get through `log` or `Program::build_log`.
+    ///
+    /// # Panics
+    /// Panics if the host or a device fails to allocate resources.
+    pub fn build(self) -> FutureBuild {
+        self.build_with_options("")
+    }
+}
+
+impl Program {
+    pub(super) unsafe fn from_ffi(program: ffi::cl_program, retain: bool) -> Self {
+        if retain {
+            catch_ffi(ffi::clRetainProgram(program)).unwrap();
+        }
+
+        Program {
+            program,
+            resolved: None,
+        }
+    }
+
+    /// Query an information to the program. `T` should be a marker type from the `information`
+    /// module implementing `ProgramInformation`.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources, or if an invalid information
+    /// param is passed (should only happen when a user incorrectly implements
+    /// `ProgramInformation` on their own or if the information is not supported on the program
+    /// and cargo features have not been set correctly, otherwise it is a bug).
+    pub fn get_info<T: information::ProgramInformation>(&self) -> T::Result {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetProgramInfo(
+                    self.program,
+                    T::id(),
+                    size,
+                    value as _,
+                    ret_size
+                )
+            })
+        };
+
+        expect!(
+            result,
+            ffi::CL_OUT_OF_RESOURCES,
+            ffi::CL_OUT_OF_HOST_MEMORY,
+            ffi::CL_INVALID_VALUE
+        )
+    }
+
+    /// Query a build information to the program. `T` should be a marker type from the `information`
+    /// module implementing `BuildInformation`.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources, or if an invalid information
+    /// param is passed (should only happen when a user incorrectly implements
+    /// `BuildInformation` on their own or if the information is not supported on the program
+    /// and cargo features have not been set correctly, otherwise it is a bug).
+    pub fn get_build_info<T: information::BuildInformation>(&self) -> T::Result {
+        let result = unsafe {
+            InformationResult::get_info(|size, value, ret_size| {
+                ffi::clGetProgramBuildInfo(
+                    self.program,
+                    self.get_info::<information::Devices>().first().unwrap().underlying(),
+                    T::id(),
+                    size,
+                    value as _,
+                    ret_size
+                )
+            })
+        };
+
+        expect!(
+            result,
+            ffi::CL_OUT_OF_RESOURCES,
+            ffi::CL_OUT_OF_HOST_MEMORY,
+            ffi::CL_INVALID_VALUE
+        )
+    }
+
+    /// Return the parsed build log. Since the log is kept on successful builds as well, this is
+    /// the way to retrieve compiler warnings. If the program was created from a `SourceTree`,
+    /// the diagnostics are reported against the original sources.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate gprust;
+    /// use gprust::{Context, program, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void my_kernel(__global float * buffer) {
+    ///         int unused;
+    ///         buffer[get_global_id(0)] *= 2;
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// if let Ok(program) = program.build_with_options("-Wall").wait() {
+    ///     for warning in program.build_log().warnings() {
+    ///         println!("{}", warning);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Panics
+    /// Same as `get_build_info`.
+    pub fn build_log(&self) -> diagnostic::Log {
+        let log = diagnostic::Log::parse(self.get_build_info::<information::BuildLog>());
+        match self.resolved {
+            Some(ref resolved) => resolved.remap(log),
+            None => log,
+        }
+    }
+
+    /// Render the diagnostics of the build log along with the offending lines of the program
+    /// source (see `Diagnostic::render`).
+    ///
+    /// # Panics
+    /// Same as `get_info`.
+    pub fn render_build_log(&self) -> String {
+        match self.resolved {
+            Some(ref resolved) => resolved.render(&self.build_log()),
+            None => self.build_log().render(&self.get_info::<information::Source>()),
+        }
+    }
+
+    /// Return a list of kernel names the program contains.
+    ///
+    /// # Panics
+    /// Same as `get_info`.
+    pub fn kernel_names(&self) -> Vec<String> {
+        self.get_info::<information::KernelNames>()
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_owned())
+            .collect()
+    }
+
+    /// Create a kernel, defined in the program matching the name `kernel_name`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate gprust;
+    /// use gprust::{Context, program, Future};
+    ///
+    /// # fn main_() -> Result<(), &'static str> {
+    /// let context = Context::default().ok_or("no default context")?;
+    /// let program = program::Builder::create_with_sources(
+    ///     Some("__kernel void my_kernel(__global float * buffer) {
+    ///         buffer[get_global_id(0)] *= 2;
+    ///     }"),
+    ///     &context
+    /// ).expect("I did provide a source");
+    /// if let Ok(program) = program.build().wait() {
+    ///     let kernel = program.create_kernel("my_kernel").expect("the kernel does exist");
+    ///     assert!(program.create_kernel("other_kernel").is_err());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() { main_().unwrap(); }
+    /// ```
+    ///
+    /// # Errors
+    /// * `kernel::CreationError::InvalidKernelName` if no kernel is named `kernel_name`.
+    /// * `kernel::CreationError::InvalidProgramExecutable` if the program was not successfully
+    /// built.
+    /// * `kernel::CreationError::InvalidKernelDefinition` if the kernel definition differs
+    /// between the devices the program was built for.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn create_kernel(&self, kernel_name: &str) -> Result<Kernel, kernel::CreationError> {
+        let kernel_name = match CString::new(kernel_name) {
+            Ok(kernel_name) => kernel_name,
+            Err(_) => return Err(kernel::CreationError::InvalidKernelName),
+        };
+
+        let mut error = 0;
+        let kernel = unsafe {
+            ffi::clCreateKernel(self.program, kernel_name.as_ptr(), &mut error)
+        };
+
+        if error == ffi::CL_INVALID_KERNEL_NAME {
+            return Err(kernel::CreationError::InvalidKernelName);
+        } else if error == ffi::CL_INVALID_PROGRAM_EXECUTABLE {
+            return Err(kernel::CreationError::InvalidProgramExecutable);
+        } else if error == ffi::CL_INVALID_KERNEL_DEFINITION {
+            return Err(kernel::CreationError::InvalidKernelDefinition);
+        }
+
+        // `clCreateKernel` already retains the kernel.
+        let result = catch_ffi(error).map(|()| unsafe { Kernel::from_ffi(kernel, false) });
+        Ok(expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY))
+    }
+
+    /// Create a kernel with a statically known signature `A`, e.g.
+    /// `(Buffer<f32>, Buffer<f32>, u32)`. See `kernel::typed::TypedKernel`.
+    ///
+    /// # Errors
+    /// * `TypedKernelError::Creation(err)` if the kernel could not be created, see
+    /// `Program::create_kernel`.
+    /// * `TypedKernelError::ArityMismatch { .. }` if the number of parameters of the kernel does
+    /// not match the signature.
+    ///
+    /// # Panics
+    /// Same as `Program::create_kernel`.
+    pub fn typed_kernel<A: KernelArgs>(&self, kernel_name: &str)
+        -> Result<TypedKernel<A>, TypedKernelError>
+    {
+        let kernel = self.create_kernel(kernel_name).map_err(TypedKernelError::Creation)?;
+        TypedKernel::new(kernel)
+    }
+
+    /// Create all the kernels defined in the program, indexed by name.
+    ///
+    /// # Errors
+    /// * `kernel::CreationError::InvalidProgramExecutable` if the program was not successfully
+    /// built.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn create_kernels(&self) -> Result<HashMap<String, Kernel>, kernel::CreationError> {
+        // We retrieve the number of kernels in the program.
+        let mut num_kernels = 0;
+        let error = unsafe {
+            ffi::clCreateKernelsInProgram(self.program, 0, ptr::null_mut(), &mut num_kernels)
+        };
+
+        if error == ffi::CL_INVALID_PROGRAM_EXECUTABLE {
+            return Err(kernel::CreationError::InvalidProgramExecutable);
+        }
+        expect!(catch_ffi(error), ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+
+        let mut kernels = vec![ptr::null_mut(); num_kernels as usize];
+        if num_kernels != 0 {
+            let result = catch_ffi(unsafe {
+                ffi::clCreateKernelsInProgram(
+                    self.program,
+                    num_kernels,
+                    kernels.as_mut_ptr(),
+                    ptr::null_mut()
+                )
+            });
+            expect!(result, ffi::CL_OUT_OF_RESOURCES, ffi::CL_OUT_OF_HOST_MEMORY);
+        }
+
+        // Do not retain the kernels since this is already done by `clCreateKernelsInProgram`.
+        Ok(
+            kernels.into_iter()
+                   .map(|k| unsafe { Kernel::from_ffi(k, false) })
+                   .map(|k| (k.get_info::<kernel::information::FunctionName>(), k))
+                   .collect()
+        )
+    }
+}
+
+map_ffi_impl!(Program, ffi::cl_program);
+
+impl Clone for Program {
+    fn clone(&self) -> Self {
+        catch_ffi(unsafe { ffi::clRetainProgram(self.program) }).unwrap();
+
+        Program {
+            program: self.program,
+            resolved: self.resolved.clone(),
+        }
+    }
+}
+
+impl PartialEq for Program {
+    fn eq(&self, other: &Self) -> bool {
+        self.program == other.program
+    }
+}
+
+impl Eq for Program { }
+
+impl fmt::Debug for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use wrapper::types::device;
+
+        f.debug_struct("Program")
+         .field(
+             "devices",
+             &self.get_info::<information::Devices>()
+                  .iter()
+                  .map(|d| d.get_info::<device::information::Name>())
+                  .collect::<Vec<_>>()
+         )
+         .finish()
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        catch_ffi(unsafe { ffi::clReleaseProgram(self.program) }).unwrap();
+    }
+}
+
+#[test]
+fn test_build_failure_diagnostics() {
+    use futures::Future;
+
+    let context = Context::default().unwrap();
+    let program = Builder::create_with_sources(
+        Some("__kernel void f(__global float * buffer) {
+            buffer[get_global_id(0)] = undeclared;
+        }"),
+        &context
+    ).unwrap();
+
+    match program.build().wait() {
+        Err(BuildError::BuildFailed(log)) => {
+            let errors = log.errors();
+            assert!(!errors.is_empty());
+            assert!(errors.iter().any(|e| e.line() == Some(2)));
+        }
+        _ => panic!("build should have failed"),
+    }
+}
+
+#[test]
+fn test_create_kernels() {
+    use futures::Future;
+
+    let context = Context::default().unwrap();
+    let program = Builder::create_with_sources(
+        Some("__kernel void first(__global float * buffer) { }
+              __kernel void second(__global int * buffer, int n) { }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+
+    let kernels = program.create_kernels().unwrap();
+    assert_eq!(kernels.len(), 2);
+    assert!(kernels.contains_key("first") && kernels.contains_key("second"));
+
+    assert!(program.create_kernel("first").is_ok());
+    assert_eq!(
+        program.create_kernel("third"),
+        Err(kernel::CreationError::InvalidKernelName)
+    );
+}
+
+#[test]
+fn test_builtin_kernels_validation() {
+    let context = Context::default().unwrap();
+    let device = Device::default().unwrap();
+
+    assert_eq!(
+        Builder::create_with_builtin_kernels(&context, None, Some("foo")),
+        Err(BuiltinKernelsError::NoDevice)
+    );
+    assert_eq!(
+        Builder::create_with_builtin_kernels(&context, Some(&device), None),
+        Err(BuiltinKernelsError::NoKernelNames)
+    );
+    assert_eq!(
+        Builder::create_with_builtin_kernels(&context, Some(&device), vec!["", " "]),
+        Err(BuiltinKernelsError::NoKernelNames)
+    );
+
+    match Builder::create_with_builtin_kernels(&context, Some(&device), Some("not_a_builtin")) {
+        Err(BuiltinKernelsError::NotSupported { kernel, .. }) => assert_eq!(kernel, "not_a_builtin"),
+        _ => panic!("`not_a_builtin` should not be supported"),
+    }
+}
+
+#[test]
+fn test_source_tree_build_log() {
+    use futures::Future;
+
+    let mut tree = source::SourceTree::new();
+    tree.add("util.h", "#pragma once\n#warning \"util.h is included\"")
+        .add("main.cl", "#include \"util.h\"
+            __kernel void f(__global float * buffer) { }");
+
+    let context = Context::default().unwrap();
+    let program = Builder::create_with_source_tree(&tree, "main.cl", &context).unwrap();
+    let program = program.build().wait().unwrap();
+
+    for d in program.build_log().diagnostics() {
+        if d.line().is_some() {
+            assert!(d.file() == Some("util.h") || d.file() == Some("main.cl"));
+        }
+    }
+    assert_eq!(program.clone(), program);
+}
@@ -0,0 +1,345 @@
+//! A module defining the `BuildOptions` builder, used for generating the options string passed
+//! to `clBuildProgram`.
+
+use wrapper::types::device::{self, Device};
+use std::fmt;
+
+/// An OpenCL C language standard, as requested through the `-cl-std=` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Standard {
+    /// OpenCL C 1.1 (`-cl-std=CL1.1`).
+    Cl11,
+
+    /// OpenCL C 1.2 (`-cl-std=CL1.2`).
+    Cl12,
+
+    /// OpenCL C 2.0 (`-cl-std=CL2.0`).
+    Cl20,
+}
+
+impl Standard {
+    fn version(self) -> (u32, u32) {
+        match self {
+            Standard::Cl11 => (1, 1),
+            Standard::Cl12 => (1, 2),
+            Standard::Cl20 => (2, 0),
+        }
+    }
+}
+
+impl fmt::Display for Standard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (major, minor) = self.version();
+        write!(f, "CL{}.{}", major, minor)
+    }
+}
+
+/// An error returned by `BuildOptions::validate`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ValidationError {
+    /// `InvalidMacroName(name)` where `name` is not a valid C identifier.
+    InvalidMacroName(String),
+
+    /// An empty include directory was given.
+    EmptyIncludeDirectory,
+
+    /// The requested standard is not supported by one of the devices. `device_version` is the
+    /// content of `device::information::OpenClCVersion` for this device.
+    UnsupportedStandard {
+        /// The requested standard.
+        requested: Standard,
+
+        /// The OpenCL C version reported by the device.
+        device_version: String,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::InvalidMacroName(ref name) =>
+                write!(f, "`{}` is not a valid macro name", name),
+            ValidationError::EmptyIncludeDirectory =>
+                write!(f, "empty include directory"),
+            ValidationError::UnsupportedStandard { requested, ref device_version } =>
+                write!(f, "`-cl-std={}` is not supported by device (`{}`)", requested, device_version),
+        }
+    }
+}
+
+/// Describe options to be passed to `clBuildProgram` a.k.a `Builder::build_with`.
+/// It is to be used as a builder, and renders to a properly quoted options string through its
+/// `Display` implementation.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::program::options::{BuildOptions, Standard};
+///
+/// # fn main() {
+/// let options = BuildOptions::new().define("N", "4")
+///                                  .include_directory("my kernels")
+///                                  .set_standard(Standard::Cl12)
+///                                  .set_fast_relaxed_math();
+/// assert_eq!(
+///     options.to_string(),
+///     "-D N=4 -I \"my kernels\" -cl-std=CL1.2 -cl-fast-relaxed-math"
+/// );
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BuildOptions {
+    defines: Vec<(String, Option<String>)>,
+    include_directories: Vec<String>,
+    standard_field: Option<Standard>,
+    flags: Vec<&'static str>,
+}
+
+impl BuildOptions {
+    /// Return an empty set of options.
+    pub fn new() -> Self {
+        BuildOptions {
+            defines: Vec::new(),
+            include_directories: Vec::new(),
+            standard_field: None,
+            flags: Vec::new(),
+        }
+    }
+
+    fn flag(mut self, flag: &'static str) -> Self {
+        if !self.flags.contains(&flag) {
+            self.flags.push(flag);
+        }
+        self
+    }
+
+    /// Define a preprocessor macro with a value (`-D name=value`).
+    pub fn define<N: Into<String>, V: ToString>(mut self, name: N, value: V) -> Self {
+        self.defines.push((name.into(), Some(value.to_string())));
+        self
+    }
+
+    /// Define a preprocessor macro without a value (`-D name`).
+    pub fn define_flag<N: Into<String>>(mut self, name: N) -> Self {
+        self.defines.push((name.into(), None));
+        self
+    }
+
+    /// Add a directory to the list of directories searched for header files (`-I dir`).
+    pub fn include_directory<D: Into<String>>(mut self, directory: D) -> Self {
+        self.include_directories.push(directory.into());
+        self
+    }
+
+    /// Return the requested OpenCL C standard if any.
+    pub fn standard(&self) -> Option<Standard> {
+        self.standard_field
+    }
+
+    /// Request a specific OpenCL C standard (`-cl-std=`).
+    pub fn set_standard(mut self, standard: Standard) -> Self {
+        self.standard_field = Some(standard);
+        self
+    }
+
+    /// Set `-cl-single-precision-constant`.
+    pub fn set_single_precision_constant(self) -> Self {
+        self.flag("-cl-single-precision-constant")
+    }
+
+    /// Set `-cl-denorms-are-zero`.
+    pub fn set_denorms_are_zero(self) -> Self {
+        self.flag("-cl-denorms-are-zero")
+    }
+
+    /// Set `-cl-opt-disable`.
+    pub fn set_opt_disable(self) -> Self {
+        self.flag("-cl-opt-disable")
+    }
+
+    /// Set `-cl-mad-enable`.
+    pub fn set_mad_enable(self) -> Self {
+        self.flag("-cl-mad-enable")
+    }
+
+    /// Set `-cl-no-signed-zeros`.
+    pub fn set_no_signed_zeros(self) -> Self {
+        self.flag("-cl-no-signed-zeros")
+    }
+
+    /// Set `-cl-unsafe-math-optimizations`.
+    pub fn set_unsafe_math_optimizations(self) -> Self {
+        self.flag("-cl-unsafe-math-optimizations")
+    }
+
+    /// Set `-cl-finite-math-only`.
+    pub fn set_finite_math_only(self) -> Self {
+        self.flag("-cl-finite-math-only")
+    }
+
+    /// Set `-cl-fast-relaxed-math`.
+    pub fn set_fast_relaxed_math(self) -> Self {
+        self.flag("-cl-fast-relaxed-math")
+    }
+
+    /// Set `-w`, inhibiting all warnings.
+    pub fn set_inhibit_warnings(self) -> Self {
+        self.flag("-w")
+    }
+
+    /// Set `-Werror`, making all warnings into errors.
+    pub fn set_warnings_as_errors(self) -> Self {
+        self.flag("-Werror")
+    }
+
+    /// Set `-cl-kernel-arg-info`, so that argument information can be queried on kernels.
+    pub fn set_kernel_arg_info(self) -> Self {
+        self.flag("-cl-kernel-arg-info")
+    }
+
+    /// Return `true` if `-cl-kernel-arg-info` was set.
+    pub fn kernel_arg_info(&self) -> bool {
+        self.flags.contains(&"-cl-kernel-arg-info")
+    }
+
+    /// Check the options for errors which would otherwise only be reported by the driver as
+    /// `CL_INVALID_BUILD_OPTIONS`, and check that the requested standard is supported by all
+    /// `devices`.
+    ///
+    /// # Errors
+    /// * `ValidationError::InvalidMacroName(name)` if a macro name is not a valid identifier.
+    /// * `ValidationError::EmptyIncludeDirectory` if an include directory is empty.
+    /// * `ValidationError::UnsupportedStandard { .. }` if one of the devices does not support
+    /// the requested standard.
+    ///
+    /// # Panics
+    /// Same as `Device::get_info`.
+    pub fn validate<'a, I: IntoIterator<Item = &'a Device>>(&self, devices: I)
+        -> Result<(), ValidationError>
+    {
+        for (name, _) in &self.defines {
+            let mut chars = name.chars();
+            let valid = match chars.next() {
+                Some(c) if c == '_' || c.is_ascii_alphabetic() =>
+                    chars.all(|c| c == '_' || c.is_ascii_alphanumeric()),
+                _ => false,
+            };
+
+            if !valid {
+                return Err(ValidationError::InvalidMacroName(name.clone()));
+            }
+        }
+
+        if self.include_directories.iter().any(|d| d.is_empty()) {
+            return Err(ValidationError::EmptyIncludeDirectory);
+        }
+
+        if let Some(requested) = self.standard_field {
+            for d in devices {
+                let device_version = d.get_info::<device::information::OpenClCVersion>();
+
+                // If the version cannot be parsed, let the driver decide.
+                if let Some(version) = device::parse_version(&device_version, "OpenCL C ") {
+                    if version < requested.version() {
+                        return Err(
+                            ValidationError::UnsupportedStandard { requested, device_version }
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Quote an option argument if needed, so that it is not split by the driver.
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.chars().any(|c| c.is_whitespace() || c == '"' || c == '\\') {
+        return value.to_owned();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl fmt::Display for BuildOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut options = Vec::new();
+
+        for (name, value) in &self.defines {
+            options.push(match *value {
+                Some(ref value) => format!("-D {}={}", name, quote(value)),
+                None => format!("-D {}", name),
+            });
+        }
+
+        for directory in &self.include_directories {
+            options.push(format!("-I {}", quote(directory)));
+        }
+
+        if let Some(standard) = self.standard_field {
+            options.push(format!("-cl-std={}", standard));
+        }
+
+        options.extend(self.flags.iter().map(|flag| flag.to_string()));
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+#[test]
+fn test_render() {
+    assert_eq!(BuildOptions::new().to_string(), "");
+
+    let options = BuildOptions::new().define("WIDTH", 16)
+                                     .define_flag("USE_DOUBLE")
+                                     .define("MSG", "say \"hi\"")
+                                     .include_directory("/opt/kernels")
+                                     .set_mad_enable()
+                                     .set_mad_enable()
+                                     .set_warnings_as_errors()
+                                     .set_kernel_arg_info();
+    assert_eq!(
+        options.to_string(),
+        "-D WIDTH=16 -D USE_DOUBLE -D MSG=\"say \\\"hi\\\"\" -I /opt/kernels \
+         -cl-mad-enable -Werror -cl-kernel-arg-info"
+    );
+    assert!(options.kernel_arg_info());
+}
+
+#[test]
+fn test_validate() {
+    let no_device: Option<&Device> = None;
+
+    assert_eq!(
+        BuildOptions::new().define("1ABC", 1).validate(no_device),
+        Err(ValidationError::InvalidMacroName("1ABC".to_owned()))
+    );
+    assert_eq!(
+        BuildOptions::new().include_directory("").validate(no_device),
+        Err(ValidationError::EmptyIncludeDirectory)
+    );
+    assert_eq!(BuildOptions::new().define("_N2", 1).validate(no_device), Ok(()));
+}
+
+#[test]
+fn test_validate_standard() {
+    let device = Device::default().unwrap();
+    let version = device::parse_version(
+        &device.get_info::<device::information::OpenClCVersion>(),
+        "OpenCL C "
+    );
+    let result = BuildOptions::new().set_standard(Standard::Cl20).validate(Some(&device));
+
+    match version {
+        Some(version) if version < (2, 0) => assert!(result.is_err()),
+        _ => assert_eq!(result, Ok(())),
+    }
+}
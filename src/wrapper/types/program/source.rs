@@ -0,0 +1,374 @@
+//! A module defining the `SourceTree` type, a virtual file system for program sources.
+//!
+//! The way `#include "file"` directives are resolved by OpenCL compilers depends on the working
+//! directory of the process and on `-I` options, which makes it hard to ship kernels with an
+//! application. A `SourceTree` instead resolves includes itself, among the sources which were
+//! registered in it, and hands a single self-contained source to `clCreateProgramWithSource`.
+//! A line map is kept so that diagnostics can be reported against the original files.
+
+use wrapper::types::program::diagnostic;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::fmt;
+
+/// An error returned by `SourceTree::resolve`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum IncludeError {
+    /// A source was not registered in the tree.
+    NotFound {
+        /// Name of the missing source.
+        name: String,
+
+        /// Name of the including source and line of the `#include` directive, or `None` if the
+        /// missing source is the root.
+        included_from: Option<(String, usize)>,
+    },
+
+    /// `Cycle(names)` where `names` is the include stack, ending with the source which was
+    /// being included again.
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IncludeError::NotFound { ref name, included_from: None } =>
+                write!(f, "source `{}` not found", name),
+            IncludeError::NotFound { ref name, included_from: Some((ref file, line)) } =>
+                write!(f, "source `{}` not found (included from {}:{})", name, file, line),
+            IncludeError::Cycle(ref names) =>
+                write!(f, "include cycle: {}", names.join(" -> ")),
+        }
+    }
+}
+
+/// A set of named sources in which `#include "name"` directives are resolved.
+///
+/// Names are virtual paths: an `#include "b.cl"` directive found in source `dir/a.cl` first
+/// looks for `dir/b.cl`, then for `b.cl`. System includes (`#include <...>`) are left to the
+/// compiler. Sources protected by `#pragma once` or by a classic `#ifndef` / `#define` include
+/// guard are only included once.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::program::source::SourceTree;
+///
+/// # fn main() {
+/// let mut tree = SourceTree::new();
+/// tree.add("common.cl", "#pragma once\n#define FACTOR 2\n")
+///     .add("main.cl", "#include \"common.cl\"\n#include \"common.cl\"\n__kernel void f() { }\n");
+///
+/// let resolved = tree.resolve("main.cl").unwrap();
+/// assert_eq!(resolved.source().matches("#define FACTOR").count(), 1);
+/// assert_eq!(resolved.locate(3), Some(("main.cl", 3)));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceTree {
+    sources: HashMap<String, String>,
+}
+
+impl SourceTree {
+    /// Return an empty tree.
+    pub fn new() -> Self {
+        SourceTree {
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Register a source under `name`, replacing any source previously registered under the same
+    /// name. `source` may come from `include_str!` or be generated at runtime.
+    pub fn add<N: Into<String>, S: Into<String>>(&mut self, name: N, source: S) -> &mut Self {
+        self.sources.insert(name.into(), source.into());
+        self
+    }
+
+    /// Register the content of the file at `path` under `name`.
+    ///
+    /// # Errors
+    /// Return an `io::Error` if the file could not be read.
+    pub fn add_file<N: Into<String>, P: AsRef<Path>>(&mut self, name: N, path: P)
+        -> io::Result<&mut Self>
+    {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        Ok(self.add(name, source))
+    }
+
+    /// Return the source registered under `name` if any.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.sources.get(name).map(|s| s.as_str())
+    }
+
+    /// Resolve all the includes of the source `root`, producing a single self-contained source.
+    ///
+    /// # Errors
+    /// * `IncludeError::NotFound { .. }` if `root` or an included source was not registered.
+    /// * `IncludeError::Cycle(names)` if sources include each other without guards.
+    pub fn resolve(&self, root: &str) -> Result<Resolved, IncludeError> {
+        if !self.sources.contains_key(root) {
+            return Err(IncludeError::NotFound { name: root.to_owned(), included_from: None });
+        }
+
+        let mut resolver = Resolver {
+            tree: self,
+            resolved: Resolved {
+                source: String::new(),
+                files: Vec::new(),
+                lines: Vec::new(),
+            },
+            stack: Vec::new(),
+            once: HashSet::new(),
+            guards: HashSet::new(),
+        };
+
+        resolver.include(root)?;
+        Ok(resolver.resolved)
+    }
+
+    // Find the name under which `name` is registered when included from `from`.
+    fn lookup(&self, name: &str, from: &str) -> Option<String> {
+        if let Some(index) = from.rfind('/') {
+            let relative = format!("{}/{}", &from[..index], name);
+            if self.sources.contains_key(&relative) {
+                return Some(relative);
+            }
+        }
+
+        if self.sources.contains_key(name) {
+            Some(name.to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+// Return the directive of a preprocessor line if any, e.g. `include "a.cl"` for `  # include "a.cl"`.
+fn directive(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix('#').map(|d| d.trim())
+}
+
+// Return the name of a quoted include, e.g. `a.cl` for `#include "a.cl"`.
+fn quoted_include(line: &str) -> Option<&str> {
+    let rest = directive(line)?.strip_prefix("include")?.trim();
+    let rest = rest.strip_prefix('"')?;
+    rest.find('"').map(|end| &rest[..end])
+}
+
+// Return the guard macro of a source protected by a classic include guard, i.e. a source whose
+// first directive is `#ifndef NAME` immediately followed by `#define NAME`.
+fn include_guard(source: &str) -> Option<&str> {
+    let mut lines = source.lines()
+                          .map(|l| l.trim())
+                          .filter(|l| !l.is_empty() && !l.starts_with("//"));
+
+    let name = directive(lines.next()?)?.strip_prefix("ifndef")?.trim();
+    let define = directive(lines.next()?)?.strip_prefix("define")?.trim();
+
+    if !name.is_empty() && define.split_whitespace().next() == Some(name) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+struct Resolver<'a> {
+    tree: &'a SourceTree,
+    resolved: Resolved,
+    stack: Vec<String>,
+    once: HashSet<String>,
+    guards: HashSet<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn include(&mut self, name: &str) -> Result<(), IncludeError> {
+        let tree = self.tree;
+        let source = &tree.sources[name];
+
+        if self.once.contains(name) {
+            return Ok(());
+        }
+
+        if let Some(guard) = include_guard(source) {
+            if !self.guards.insert(guard.to_owned()) {
+                return Ok(());
+            }
+        }
+
+        if self.stack.iter().any(|n| n == name) {
+            let mut cycle = self.stack.clone();
+            cycle.push(name.to_owned());
+            return Err(IncludeError::Cycle(cycle));
+        }
+
+        let file = match self.resolved.files.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.resolved.files.push((name.to_owned(), source.clone()));
+                self.resolved.files.len() - 1
+            }
+        };
+
+        self.stack.push(name.to_owned());
+        for (index, line) in source.lines().enumerate() {
+            if directive(line) == Some("pragma once") {
+                self.once.insert(name.to_owned());
+                self.push_line(file, index + 1, "");
+            } else if let Some(included) = quoted_include(line) {
+                let found = self.tree.lookup(included, name).ok_or_else(|| {
+                    IncludeError::NotFound {
+                        name: included.to_owned(),
+                        included_from: Some((name.to_owned(), index + 1)),
+                    }
+                })?;
+                self.include(&found)?;
+            } else {
+                self.push_line(file, index + 1, line);
+            }
+        }
+        self.stack.pop();
+
+        Ok(())
+    }
+
+    fn push_line(&mut self, file: usize, line: usize, content: &str) {
+        self.resolved.source.push_str(content);
+        self.resolved.source.push('\n');
+        self.resolved.lines.push((file, line));
+    }
+}
+
+/// A source produced by `SourceTree::resolve`, together with a map from its lines to the lines
+/// of the original sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    source: String,
+    files: Vec<(String, String)>,
+    lines: Vec<(usize, usize)>,
+}
+
+impl Resolved {
+    /// Return the resolved source, to be passed to `clCreateProgramWithSource`.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Return the name of the original source and the line number in this source corresponding
+    /// to the (1-based) `line` of the resolved source, if any.
+    pub fn locate(&self, line: usize) -> Option<(&str, usize)> {
+        if line == 0 {
+            return None;
+        }
+
+        self.lines.get(line - 1).map(|&(file, line)| (self.files[file].0.as_str(), line))
+    }
+
+    /// Map the locations of the diagnostics of `log`, which refer to lines of the resolved
+    /// source, back to the original sources.
+    pub fn remap(&self, mut log: diagnostic::Log) -> diagnostic::Log {
+        for d in log.diagnostics_mut() {
+            let location = d.line()
+                            .and_then(|line| self.locate(line))
+                            .map(|(file, line)| (file.to_owned(), line));
+
+            if let Some((file, line)) = location {
+                d.relocate(file, line);
+            }
+        }
+
+        log
+    }
+
+    /// Render the diagnostics of a log returned by `remap` against the original sources (see
+    /// `Diagnostic::render`).
+    pub fn render(&self, log: &diagnostic::Log) -> String {
+        log.diagnostics()
+           .iter()
+           .map(|d| {
+               let source = d.file()
+                             .and_then(|name| self.files.iter().find(|(n, _)| n == name))
+                             .map(|(_, source)| source.as_str())
+                             .unwrap_or("");
+               d.render(source)
+           })
+           .collect()
+    }
+}
+
+#[test]
+fn test_resolve() {
+    let mut tree = SourceTree::new();
+    tree.add("kernels/main.cl", "#include \"util.cl\"\n# include \"common.cl\"\n__kernel void f() { }")
+        .add("kernels/util.cl", "float twice(float x) { return 2 * x; }")
+        .add("common.cl", "#include <system.h>\n#define N 4");
+
+    let resolved = tree.resolve("kernels/main.cl").unwrap();
+    assert_eq!(
+        resolved.source(),
+        "float twice(float x) { return 2 * x; }\n#include <system.h>\n#define N 4\n__kernel void f() { }\n"
+    );
+    assert_eq!(resolved.locate(1), Some(("kernels/util.cl", 1)));
+    assert_eq!(resolved.locate(3), Some(("common.cl", 2)));
+    assert_eq!(resolved.locate(4), Some(("kernels/main.cl", 3)));
+    assert_eq!(resolved.locate(5), None);
+}
+
+#[test]
+fn test_include_guards() {
+    let mut tree = SourceTree::new();
+    tree.add("a.h", "// header\n#ifndef A_H\n#define A_H\nint a;\n#endif")
+        .add("b.h", "#pragma once\nint b;")
+        .add("main.cl", "#include \"a.h\"\n#include \"b.h\"\n#include \"a.h\"\n#include \"b.h\"");
+
+    let source = tree.resolve("main.cl").unwrap().source().to_owned();
+    assert_eq!(source.matches("int a;").count(), 1);
+    assert_eq!(source.matches("int b;").count(), 1);
+    assert!(!source.contains("pragma once"));
+}
+
+#[test]
+fn test_errors() {
+    let mut tree = SourceTree::new();
+    tree.add("a.cl", "#include \"b.cl\"")
+        .add("b.cl", "#include \"a.cl\"")
+        .add("c.cl", "\n#include \"missing.cl\"");
+
+    assert_eq!(
+        tree.resolve("a.cl"),
+        Err(IncludeError::Cycle(vec!["a.cl".to_owned(), "b.cl".to_owned(), "a.cl".to_owned()]))
+    );
+    assert_eq!(
+        tree.resolve("c.cl"),
+        Err(IncludeError::NotFound {
+            name: "missing.cl".to_owned(),
+            included_from: Some(("c.cl".to_owned(), 2)),
+        })
+    );
+    assert_eq!(
+        tree.resolve("d.cl"),
+        Err(IncludeError::NotFound { name: "d.cl".to_owned(), included_from: None })
+    );
+}
+
+#[test]
+fn test_remap() {
+    let mut tree = SourceTree::new();
+    tree.add("util.cl", "int util() {\n    return x;\n}")
+        .add("main.cl", "#include \"util.cl\"\n__kernel void f() { }");
+
+    let resolved = tree.resolve("main.cl").unwrap();
+    let log = resolved.remap(
+        diagnostic::Log::parse("<source>:2:12: error: use of undeclared identifier 'x'".to_owned())
+    );
+
+    let error = &log.diagnostics()[0];
+    assert_eq!(error.file(), Some("util.cl"));
+    assert_eq!((error.line(), error.column()), (Some(2), Some(12)));
+    assert_eq!(
+        resolved.render(&log),
+        "util.cl:2:12: error: use of undeclared identifier 'x'\n    return x;\n           ^\n"
+    );
+}
@@ -0,0 +1,383 @@
+//! A module defining `Stream`, for streaming chunks of data through a kernel.
+
+use wrapper::types::command_queue::{self, CommandQueue, WaitList, LaunchError, TransferError};
+use wrapper::types::mem::{self, Buffer};
+use wrapper::types::event::{Event, ExecutionError};
+use std::time::{Duration, Instant};
+use std::mem::size_of_val;
+use std::ptr;
+use std::fmt;
+
+/// An error returned by `Stream::create` and `Stream::run`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum StreamError {
+    /// The transfer and compute queues are not associated with the same context.
+    InvalidContext,
+
+    /// A staging or device buffer could not be created.
+    Buffer(mem::CreationError),
+
+    /// The chunk at position `index` is empty or larger than the chunk length of the stream.
+    InvalidChunk {
+        /// The position of the chunk in the iterator.
+        index: usize
+    },
+
+    /// A memory transfer failed.
+    Transfer(TransferError),
+
+    /// The launch function returned an error.
+    Launch(LaunchError),
+
+    /// A command was abnormally terminated.
+    Execution(ExecutionError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StreamError::InvalidContext =>
+                write!(f, "the command queues are not associated with the same context"),
+            StreamError::Buffer(err) =>
+                write!(f, "failed to create a buffer: {}", err),
+            StreamError::InvalidChunk { index } =>
+                write!(f, "chunk {} is empty or larger than the chunk length", index),
+            StreamError::Transfer(err) =>
+                write!(f, "{}", err),
+            StreamError::Launch(ref err) =>
+                write!(f, "{}", err),
+            StreamError::Execution(ExecutionError(code)) =>
+                write!(f, "a command was abnormally terminated with status {}", code),
+        }
+    }
+}
+
+/// A report of a `Stream::run` call.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Report {
+    chunks: usize,
+    bytes: usize,
+    elapsed: Duration,
+}
+
+impl Report {
+    /// Return the number of chunks streamed.
+    pub fn chunks(&self) -> usize {
+        self.chunks
+    }
+
+    /// Return the number of bytes uploaded to the device.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Return the wall-clock time elapsed from the first chunk until the last kernel completed.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Return the achieved throughput, in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 { self.bytes as f64 / seconds } else { 0.0 }
+    }
+}
+
+// A staging buffer, mapped for the lifetime of the stream, and the device buffer it is copied
+// to, with the last commands using them.
+struct Slot<T> {
+    staging: Buffer<T>,
+    mapped: *mut T,
+    device: Buffer<T>,
+    copied: Option<Event>,
+    launched: Option<Event>,
+}
+
+/// A helper for streaming chunks of data from the host through a kernel. Each chunk is written
+/// into a pinned staging buffer (allocated with `CL_MEM_ALLOC_HOST_PTR` and mapped once when
+/// the stream is created), copied to a device buffer on a transfer queue, and handed to a
+/// launch function which enqueues kernels on a compute queue.
+///
+/// The stream cycles through `in_flight` pairs of staging and device buffers, so that filling
+/// a staging buffer on the host, copying another one to the device and running kernels on a
+/// third one overlap: before filling a staging buffer, the stream only waits for its own
+/// previous copy. Using two command queues lets the device overlap transfers and kernel
+/// executions; they may be the same queue though.
+///
+/// # Examples
+/// ```
+/// # extern crate gprust;
+/// use gprust::{CommandQueue, Stream, command_queue, program, Future};
+///
+/// # fn main_() -> Result<(), &'static str> {
+/// let queue = CommandQueue::default().ok_or("no default command queue")?;
+/// let context = queue.get_info::<command_queue::information::Context>();
+/// let program = program::Builder::create_with_sources(
+///     Some("__kernel void scale(__global float * data) {
+///         data[get_global_id(0)] *= 2.0f;
+///     }"),
+///     &context
+/// ).expect("I did provide a source");
+/// let program = program.build().wait().map_err(|_| "build failed")?;
+/// let mut kernel = program.create_kernel("scale").map_err(|_| "no such kernel")?;
+///
+/// let mut stream = Stream::<f32>::create(&queue, &queue, 1 << 16, 2)
+///     .map_err(|_| "failed to create stream")?;
+/// let chunks = (0..16).map(|i| vec![i as f32; 1 << 16]);
+///
+/// let report = stream.run(chunks, |wait_list, buffer, len| {
+///     kernel.set_arg(0, buffer).unwrap();
+///     wait_list.enqueue_nd_range(&kernel, &command_queue::NdRange::new(&[len]))
+/// }).map_err(|_| "streaming failed")?;
+///
+/// println!("{} bytes/s", report.throughput());
+/// # Ok(())
+/// # }
+/// # fn main() { main_().unwrap(); }
+/// ```
+pub struct Stream<T> {
+    transfer: CommandQueue,
+    compute: CommandQueue,
+    slots: Vec<Slot<T>>,
+    chunk_len: usize,
+}
+
+// The mapped pointers are only used through `&mut self`.
+unsafe impl<T: Send> Send for Stream<T> { }
+
+impl<T: Copy + Default> Stream<T> {
+    /// Create a stream of chunks of at most `chunk_len` elements, with `in_flight` pairs of
+    /// staging and device buffers (2 for double-buffering). Transfers are enqueued on `transfer`
+    /// and kernels on `compute`.
+    ///
+    /// # Errors
+    /// * `StreamError::InvalidContext` if the queues are not associated with the same context.
+    /// * `StreamError::Buffer(err)` if a buffer could not be created.
+    /// * `StreamError::Transfer(err)` if a staging buffer could not be mapped.
+    ///
+    /// # Panics
+    /// Panic if `in_flight == 0`, or same as `Buffer::create`.
+    pub fn create(transfer: &CommandQueue, compute: &CommandQueue, chunk_len: usize,
+                  in_flight: usize)
+        -> Result<Self, StreamError>
+    {
+        assert!(in_flight > 0, "at least one chunk should be in flight");
+
+        let context = transfer.get_info::<command_queue::information::Context>();
+        if context != compute.get_info::<command_queue::information::Context>() {
+            return Err(StreamError::InvalidContext);
+        }
+
+        let staging_flags = mem::FlagsBuilder::new().alloc_host_ptr()
+                                                    .read_only()
+                                                    .host_write_only()
+                                                    .finish();
+        let slots = (0..in_flight).map(|_| {
+            let staging = Buffer::create(vec![T::default(); chunk_len], &context, staging_flags)
+                .map_err(StreamError::Buffer)?;
+            let device = Buffer::create(vec![T::default(); chunk_len], &context, mem::Flags::new())
+                .map_err(StreamError::Buffer)?;
+            let mapped = transfer.after(&[])
+                                 .map_write(&staging, chunk_len)
+                                 .map_err(StreamError::Transfer)?;
+
+            Ok(Slot {
+                staging,
+                mapped,
+                device,
+                copied: None,
+                launched: None,
+            })
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Stream {
+            transfer: transfer.clone(),
+            compute: compute.clone(),
+            slots,
+            chunk_len,
+        })
+    }
+
+    /// Stream `chunks` to the device, calling `launch(wait_list, buffer, len)` for each chunk
+    /// once its copy has been enqueued: `buffer` is the device buffer holding the chunk in its
+    /// first `len` elements, and `wait_list` is a wait list of the compute queue on which
+    /// kernels using `buffer` should be enqueued. The returned event should complete once
+    /// `buffer` is not used anymore, as it will then be reused for a later chunk.
+    ///
+    /// Block until the last kernels have completed, and return a report of the run.
+    ///
+    /// # Errors
+    /// * `StreamError::InvalidChunk { index }` if a chunk is empty or larger than the chunk
+    /// length of the stream.
+    /// * `StreamError::Transfer(err)` if a transfer failed.
+    /// * `StreamError::Launch(err)` if `launch` returned an error.
+    /// * `StreamError::Execution(err)` if a command was abnormally terminated.
+    ///
+    /// In case of error, the commands already enqueued are still run.
+    ///
+    /// # Panics
+    /// Panic if the host or a device fails to allocate resources.
+    pub fn run<I, C, F>(&mut self, chunks: I, mut launch: F) -> Result<Report, StreamError>
+        where I: IntoIterator<Item = C>,
+              C: AsRef<[T]>,
+              F: FnMut(WaitList, &Buffer<T>, usize) -> Result<Event, LaunchError>
+    {
+        let start = Instant::now();
+        let mut report = Report {
+            chunks: 0,
+            bytes: 0,
+            elapsed: Duration::from_secs(0),
+        };
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let chunk = chunk.as_ref();
+            if chunk.is_empty() || chunk.len() > self.chunk_len {
+                return Err(StreamError::InvalidChunk { index });
+            }
+
+            let in_flight = self.slots.len();
+            let slot = &mut self.slots[index % in_flight];
+
+            // The staging buffer can be filled again once its previous copy has completed, while
+            // the commands of the other slots keep running.
+            if let Some(copied) = slot.copied.take() {
+                copied.wait_for_completion().map_err(StreamError::Execution)?;
+            }
+            unsafe { ptr::copy_nonoverlapping(chunk.as_ptr(), slot.mapped, chunk.len()) };
+
+            // The device buffer can be overwritten once the previous kernels using it have
+            // completed.
+            let launched: Vec<_> = slot.launched.iter().collect();
+            let copied = unsafe {
+                self.transfer.after(&launched).write_from(&slot.device, slot.mapped, chunk.len())
+            }.map_err(StreamError::Transfer)?;
+            self.transfer.flush();
+
+            let launched = launch(self.compute.after(&[&copied]), &slot.device, chunk.len())
+                .map_err(StreamError::Launch)?;
+            self.compute.flush();
+
+            slot.copied = Some(copied);
+            slot.launched = Some(launched);
+            report.chunks += 1;
+            report.bytes += size_of_val(chunk);
+        }
+
+        for slot in &mut self.slots {
+            slot.copied = None;
+            if let Some(launched) = slot.launched.take() {
+                launched.wait_for_completion().map_err(StreamError::Execution)?;
+            }
+        }
+
+        report.elapsed = start.elapsed();
+        Ok(report)
+    }
+}
+
+impl<T> Stream<T> {
+    /// Return the maximum number of elements of a chunk.
+    pub fn chunk_len(&self) -> usize {
+        self.chunk_len
+    }
+
+    /// Return the number of chunks which can be in flight at once.
+    pub fn in_flight(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+impl<T> Drop for Stream<T> {
+    fn drop(&mut self) {
+        for slot in &self.slots {
+            // The unmap command waits for the pending copies from the mapped memory.
+            let copied: Vec<_> = slot.copied.iter().collect();
+            let _ = unsafe { self.transfer.after(&copied).unmap(&slot.staging, slot.mapped) };
+        }
+    }
+}
+
+impl<T> fmt::Debug for Stream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stream")
+         .field("chunk_len", &self.chunk_len)
+         .field("in_flight", &self.slots.len())
+         .finish()
+    }
+}
+
+#[test]
+fn test_stream() {
+    use wrapper::types::program;
+    use futures::Future;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let device = queue.get_info::<command_queue::information::Device>();
+    let compute = CommandQueue::create(&context, &device, command_queue::Properties::new())
+        .unwrap();
+    let program = program::Builder::create_with_sources(
+        Some("__kernel void increment(__global int * data) {
+            data[get_global_id(0)] += 1;
+        }"),
+        &context
+    ).unwrap();
+    let program = program.build().wait().unwrap();
+    let mut kernel = program.create_kernel("increment").unwrap();
+
+    let mut stream = Stream::<i32>::create(&queue, &compute, 8, 2).unwrap();
+    let chunks: Vec<Vec<i32>> = (0..5).map(|i| (i * 8..i * 8 + 8).collect()).collect();
+    let mut output = vec![];
+
+    let report = stream.run(&chunks, |wait_list, buffer, len| {
+        kernel.set_arg(0, buffer).unwrap();
+        let event = wait_list.enqueue_nd_range(&kernel, &command_queue::NdRange::new(&[len]))?;
+
+        let mut data = vec![0; len];
+        wait_list.queue().after(&[&event]).enqueue_read_buffer(buffer, 0, &mut data).unwrap();
+        output.extend(data);
+        Ok(event)
+    }).unwrap();
+
+    assert_eq!(output, (1..41).collect::<Vec<_>>());
+    assert_eq!((report.chunks(), report.bytes()), (5, 40 * 4));
+
+    assert_eq!(
+        stream.run(vec![vec![0; 9]], |_, _, _| unreachable!()).unwrap_err(),
+        StreamError::InvalidChunk { index: 0 }
+    );
+}
+
+#[test]
+fn test_stream_overlap() {
+    use wrapper::types::event::{Status, UserEvent};
+    use std::cell::RefCell;
+
+    let queue = CommandQueue::default().unwrap();
+    let context = queue.get_info::<command_queue::information::Context>();
+    let mut stream = Stream::<i32>::create(&queue, &queue, 4, 2).unwrap();
+
+    // Hold the transfer queue until the third chunk is requested.
+    let gate = UserEvent::create(&context);
+    queue.after(&[&*gate]).enqueue_marker().unwrap();
+    let gate = RefCell::new(Some(gate));
+    let copies = RefCell::new(Vec::<Event>::new());
+
+    let chunks = (0..4).map(|index| {
+        // Filling chunk 1 does not wait for the copy of chunk 0, which is still pending.
+        if index == 1 {
+            assert_ne!(copies.borrow()[0].status(), Status::Complete);
+        } else if index == 2 {
+            gate.borrow_mut().take().unwrap().complete();
+        }
+        vec![index; 4]
+    });
+
+    let report = stream.run(chunks, |wait_list, _, _| {
+        copies.borrow_mut().push(wait_list.events()[0].clone());
+        wait_list.enqueue_marker().map_err(LaunchError::WaitList)
+    }).unwrap();
+
+    assert_eq!(report.chunks(), 4);
+    assert!(copies.borrow().iter().all(|copy| copy.status() == Status::Complete));
+}